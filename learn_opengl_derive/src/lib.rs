@@ -0,0 +1,146 @@
+extern crate proc_macro;
+extern crate syn;
+#[macro_use]
+extern crate quote;
+
+use proc_macro::TokenStream;
+
+use syn::{Body, DeriveInput, Field, Lit, MetaItem, NestedMetaItem, VariantData};
+
+// `#[derive(Vertex)]` implements `learn_opengl::vertex_array::Vertex` for a `#[repr(C)]` struct,
+// with one vertex attribute per field:
+//
+//     #[repr(C)]
+//     #[derive(Clone, Copy, Vertex)]
+//     struct TexturedVertex {
+//         position: [f32; 3],
+//         #[location = 2]
+//         tex_coord: [f32; 2],
+//         #[normalized]
+//         #[attribute = "vertex_color"]
+//         color: [u8; 4],
+//     }
+//
+// Attributes get the location of their field's position in the struct unless `#[location = n]`
+// says otherwise. `#[attribute = "name"]` names the shader input instead, so that
+// `VertexArray::attach_vertices_for` can look its location up in the linked program.
+// `#[normalized]` maps integer fields to [0, 1] (or [-1, 1]) floats.
+//
+// The offsets are taken from the struct itself, so they always match what the compiler laid out.
+// Field types must implement `AttributeFormat`, so an unsupported field type is a compile error.
+#[proc_macro_derive(Vertex, attributes(location, attribute, normalized))]
+pub fn derive_vertex(input: TokenStream) -> TokenStream {
+    let source = input.to_string();
+    let ast = syn::parse_derive_input(&source).unwrap();
+
+    impl_vertex(&ast).parse().unwrap()
+}
+
+fn impl_vertex(ast: &DeriveInput) -> quote::Tokens {
+    let name = &ast.ident;
+
+    if !has_repr_c(ast) {
+        panic!("#[derive(Vertex)] requires `{}` to be #[repr(C)]", name);
+    }
+
+    let fields = match ast.body {
+        Body::Struct(VariantData::Struct(ref fields)) => fields,
+        _ => panic!("#[derive(Vertex)] only supports structs with named fields"),
+    };
+
+    let attributes: Vec<_> = fields.iter().enumerate()
+        .map(|(index, field)| {
+            let field_name = field.ident.as_ref().unwrap();
+            let ty = &field.ty;
+            let location = location(field).unwrap_or(index as u32);
+
+            let normalized = if has_word_attribute(field, "normalized") {
+                quote! { .normalized() }
+            } else {
+                quote! {}
+            };
+
+            let named = match attribute_name(field) {
+                Some(attribute_name) => quote! { .named(#attribute_name) },
+                None => quote! {},
+            };
+
+            quote! {
+                .with_attribute_at(
+                    _lgl::vertex_array::VertexAttribute::new(
+                        #location,
+                        <#ty as _lgl::vertex_array::AttributeFormat>::components(),
+                        <#ty as _lgl::vertex_array::AttributeFormat>::attribute_type()
+                    )#normalized#named,
+                    unsafe { ::std::ptr::addr_of!((*base).#field_name) as usize - base as usize }
+                )
+            }
+        })
+        .collect();
+
+    // The impl lives in an anonymous const so that it can bring the library crate into scope under
+    // a known name, whatever the user's crate calls it. Rustc warns about impls inside named
+    // consts (`non_local_definitions`), but not inside anonymous ones.
+    quote! {
+        const _: () = {
+            extern crate learn_opengl as _lgl;
+
+            impl _lgl::vertex_array::Vertex for #name {
+                fn layout() -> _lgl::vertex_array::VertexLayout {
+                    // We only need an address to take field offsets from, so the vertex is never
+                    // initialized. `addr_of!` gets the field addresses without creating references
+                    // to uninitialized memory.
+                    let vertex = ::std::mem::MaybeUninit::<#name>::uninit();
+                    let base = vertex.as_ptr();
+
+                    _lgl::vertex_array::VertexLayout::new()
+                        #(#attributes)*
+                        .with_stride(::std::mem::size_of::<#name>())
+                }
+            }
+        };
+    }
+}
+
+fn has_repr_c(ast: &DeriveInput) -> bool {
+    ast.attrs.iter().any(|attr| match attr.value {
+        MetaItem::List(ref ident, ref items) if ident == "repr" => {
+            items.iter().any(|item| match *item {
+                NestedMetaItem::MetaItem(MetaItem::Word(ref word)) => word == "C",
+                _ => false,
+            })
+        }
+        _ => false,
+    })
+}
+
+fn location(field: &Field) -> Option<u32> {
+    field.attrs.iter()
+        .filter_map(|attr| match attr.value {
+            MetaItem::NameValue(ref ident, ref lit) if ident == "location" => match *lit {
+                Lit::Int(value, _) => Some(value as u32),
+                _ => panic!("#[location] must be an integer, eg. #[location = 0]"),
+            },
+            _ => None,
+        })
+        .next()
+}
+
+fn attribute_name(field: &Field) -> Option<String> {
+    field.attrs.iter()
+        .filter_map(|attr| match attr.value {
+            MetaItem::NameValue(ref ident, ref lit) if ident == "attribute" => match *lit {
+                Lit::Str(ref value, _) => Some(value.clone()),
+                _ => panic!("#[attribute] must be a string, eg. #[attribute = \"position\"]"),
+            },
+            _ => None,
+        })
+        .next()
+}
+
+fn has_word_attribute(field: &Field, name: &str) -> bool {
+    field.attrs.iter().any(|attr| match attr.value {
+        MetaItem::Word(ref ident) => ident == name,
+        _ => false,
+    })
+}
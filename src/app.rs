@@ -0,0 +1,138 @@
+use gl;
+use gl::types::*;
+use glutin::{Event, Window};
+
+use build_window;
+use screenshot::ScreenshotTrigger;
+use time::Clock;
+
+// An application run by `run`, which owns the window and calls these methods from its main loop.
+//
+// There's no context until `run` creates the window, so anything that creates OpenGL objects
+// belongs in `init` rather than in the application's constructor.
+pub trait App {
+    // Called once, with the context current, before any other method.
+    fn init(&mut self, _window: &Window) {}
+
+    // Called once per frame before `render`, with the time since the previous frame in seconds.
+    fn update(&mut self, _dt: f32) {}
+
+    fn render(&mut self);
+
+    // Called for every window event, including `Event::Closed`, right before `run` returns.
+    fn on_event(&mut self, _event: &Event) {}
+
+    // Called with the size of the window in pixels after `init`, and whenever the window is
+    // resized. `run` has already updated the viewport to match.
+    fn on_resize(&mut self, _width: u32, _height: u32) {}
+}
+
+// When `run` draws a new frame.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Redraw {
+    // As often as possible, limited by vsync. For anything animated.
+    Continuous,
+    // Only after window events, sleeping in between. For still scenes.
+    OnDemand,
+}
+
+pub struct Config {
+    pub title: String,
+    pub width: u32,
+    pub height: u32,
+    // Asks for a debug context, see `create_window`.
+    pub debug: bool,
+    pub redraw: Redraw,
+    // Takes screenshots of the frames, see `ScreenshotTrigger`. When it takes the screenshot
+    // requested with `SCREENSHOT_AFTER_FRAMES`, `run` returns.
+    pub screenshots: Option<ScreenshotTrigger>,
+}
+
+impl Config {
+    // A 1024x768 window that redraws continuously.
+    pub fn new(title: &str) -> Config {
+        Config {
+            title: title.to_string(),
+            width: 1024,
+            height: 768,
+            debug: false,
+            redraw: Redraw::Continuous,
+            screenshots: None,
+        }
+    }
+}
+
+// Creates a window as described by `config` and runs `app` in it, until the window is closed.
+//
+//     app::run(MyApp::new(), Config {
+//         redraw: Redraw::OnDemand,
+//         ..Config::new("My App")
+//     });
+//
+// `app` is dropped before the window, while the context is still current, so it can clean up its
+// OpenGL objects in `Drop`.
+pub fn run<A: App>(mut app: A, config: Config) {
+    let window = build_window(&config.title, config.width, config.height, config.debug);
+    let mut screenshots = config.screenshots;
+
+    app.init(&window);
+
+    let (width, height) = window.get_inner_size_pixels().unwrap_or((config.width, config.height));
+    resize(&mut app, width, height);
+
+    let mut clock = Clock::new();
+
+    loop {
+        // With on-demand redraws, block until something happens, then handle whatever else is
+        // queued so we draw once for the lot.
+        if config.redraw == Redraw::OnDemand {
+            if let Some(event) = window.wait_events().next() {
+                if !handle_event(&mut app, &mut screenshots, event) {
+                    break;
+                }
+            }
+        }
+
+        if !window.poll_events().all(|event| handle_event(&mut app, &mut screenshots, event)) {
+            break;
+        }
+
+        app.update(clock.tick());
+        app.render();
+
+        // Press F12 to save a screenshot.
+        if let Some(ref mut screenshots) = screenshots {
+            if screenshots.end_frame(&window) {
+                break;
+            }
+        }
+
+        window.swap_buffers().unwrap();
+    }
+
+    drop(app);
+}
+
+// Passes `event` on to `app`. Returns false when the window was closed.
+fn handle_event<A: App>(app: &mut A, screenshots: &mut Option<ScreenshotTrigger>, event: Event) -> bool {
+    if let Some(ref mut screenshots) = *screenshots {
+        screenshots.handle_event(&event);
+    }
+
+    if let Event::Resized(width, height) = event {
+        resize(app, width, height);
+    }
+
+    app.on_event(&event);
+
+    match event {
+        Event::Closed => false,
+        _ => true,
+    }
+}
+
+fn resize<A: App>(app: &mut A, width: u32, height: u32) {
+    unsafe { gl::Viewport(0, 0, width as GLsizei, height as GLsizei); }
+
+    app.on_resize(width, height);
+}
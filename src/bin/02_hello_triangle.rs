@@ -1,10 +1,13 @@
-extern crate glutin;
+extern crate learn_opengl as lgl;
 extern crate gl;
 
 use std::mem;
 use std::ptr;
 
-use glutin::{Window, Event};
+use lgl::Window;
+use lgl::app::{self, App, Config, Redraw};
+use lgl::buffer::{Buffer, BufferTarget, BufferUsage};
+use lgl::screenshot::ScreenshotTrigger;
 
 use gl::types::*;
 
@@ -34,101 +37,127 @@ void main() {
 }
 "#;
 
-fn main() {
-    // We already know how to create a window, so let's go ahead and just do it.
-    let window = create_window("Hello Triangle");
-
-    // OpenGL uses a graphics pipeline to transform 3D into colored pixels. We can hook into the
-    // pipeline steps by writing our own shaders.
-    //
-    // OpenGL requires us to provide a vertex and fragment shader.
-    let vertex_shader   = compile_shader(VERTEX_SHADER_SRC, gl::VERTEX_SHADER).unwrap();
-    let fragment_shader = compile_shader(FRAGMENT_SHADER_SRC, gl::FRAGMENT_SHADER).unwrap();
-
-    // Now we construct a program from the compiled shaders. The program knows how to feed data
-    // from and to shaders.
-    let program = link_program(vertex_shader, fragment_shader).unwrap();
-
-    // After linking the program, we can clean up the shaders so that OpenGL can free up memory.
-    cleanup_shader(vertex_shader, program);
-    cleanup_shader(fragment_shader, program);
-
-    // Next, we'll upload the triangle vertices to the GPU, where they'll be processed by the program
-    // we just linked.
-    //
-    // But before we do that, we'll create a Vertex Array Object. This will let us store vertex
-    // attribute pointer configuration so that we only have to do it once. Whenever we use the VBO
-    // again, we only have to make sure the corresponding VAO is also bound for the same
-    // configuration to be used.
-
-    let mut vao = 0;
-    unsafe {
-        // Create and bind a VAO
-        gl::GenVertexArrays(1, &mut vao);
-        gl::BindVertexArray(vao);
-    }
+// Everything we need to draw a frame. Keeping it apart from the window lets the golden-image tests
+// (see `tests/golden.rs`) draw the same thing without one.
+pub struct Scene {
+    program: GLuint,
+    vao: GLuint,
+    _vbo: Buffer<GLfloat>,
+}
 
-    // Create a Vertex Buffer Object and upload the vertices to the GPU.
-    let vbo = create_vbo(&VERTICES);
+impl Scene {
+    pub fn new() -> Scene {
+        // OpenGL uses a graphics pipeline to transform 3D into colored pixels. We can hook into the
+        // pipeline steps by writing our own shaders.
+        //
+        // OpenGL requires us to provide a vertex and fragment shader.
+        let vertex_shader   = compile_shader(VERTEX_SHADER_SRC, gl::VERTEX_SHADER).unwrap();
+        let fragment_shader = compile_shader(FRAGMENT_SHADER_SRC, gl::FRAGMENT_SHADER).unwrap();
+
+        // Now we construct a program from the compiled shaders. The program knows how to feed data
+        // from and to shaders.
+        let program = link_program(vertex_shader, fragment_shader).unwrap();
+
+        // After linking the program, we can clean up the shaders so that OpenGL can free up memory.
+        cleanup_shader(vertex_shader, program);
+        cleanup_shader(fragment_shader, program);
+
+        // Next, we'll upload the triangle vertices to the GPU, where they'll be processed by the
+        // program we just linked.
+        //
+        // But before we do that, we'll create a Vertex Array Object. This will let us store vertex
+        // attribute pointer configuration so that we only have to do it once. Whenever we use the
+        // VBO again, we only have to make sure the corresponding VAO is also bound for the same
+        // configuration to be used.
+
+        let mut vao = 0;
+        unsafe {
+            // Create and bind a VAO
+            gl::GenVertexArrays(1, &mut vao);
+            gl::BindVertexArray(vao);
+        }
 
-    unsafe {
-        // And now, we configure the vertex attributes. This instructs the vertex shader how to
-        // interpret the vertex data.
-        let stride = (3 * mem::size_of::<GLfloat>()) as GLsizei;
-        gl::VertexAttribPointer(
-            0, // The index of the generic vertex attribute to be modified.
-            3, // The number of components per generic vertex attribute. vec3, in this case.
-            gl::FLOAT,  // The data type of each component in the array
-            gl::FALSE,  // Whether the data values should be normalized.
-            stride,     // The byte offset between consecutive generic vertex attributes.
-                        // We could have passed 0, which would mean the data is tightly packed.
-            ptr::null() // Offset of the first component of the first generic vertex attribute.
-                        // NULL means 0.
-        );
-        gl::EnableVertexAttribArray(0);
+        // Create a Vertex Buffer Object and upload the vertices to the GPU. `StaticDraw` is the
+        // expected usage pattern for this data: it doesn't change, and is used for drawing.
+        let vbo = Buffer::new(BufferTarget::Vertex, &VERTICES, BufferUsage::StaticDraw);
+
+        // The vertex attributes configured below read from the vertex buffer that is bound.
+        vbo.bind();
 
-        // Tell OpenGL to use the program in the rendering pipeline.
-        gl::UseProgram(program);
+        unsafe {
+            // And now, we configure the vertex attributes. This instructs the vertex shader how to
+            // interpret the vertex data.
+            let stride = (3 * mem::size_of::<GLfloat>()) as GLsizei;
+            gl::VertexAttribPointer(
+                0, // The index of the generic vertex attribute to be modified.
+                3, // The number of components per generic vertex attribute. vec3, in this case.
+                gl::FLOAT,  // The data type of each component in the array
+                gl::FALSE,  // Whether the data values should be normalized.
+                stride,     // The byte offset between consecutive generic vertex attributes.
+                            // We could have passed 0, which would mean the data is tightly packed.
+                ptr::null() // Offset of the first component of the first generic vertex attribute.
+                            // NULL means 0.
+            );
+            gl::EnableVertexAttribArray(0);
+
+            // Tell OpenGL to use the program in the rendering pipeline.
+            gl::UseProgram(program);
+        }
+
+        Scene {
+            program,
+            vao,
+            _vbo: vbo,
+        }
     }
 
-    for event in window.wait_events() {
+    pub fn draw(&self) {
         unsafe {
             // Lastly, we draw the object.
             gl::ClearColor(0.3, 0.3, 0.3, 1.0);
             gl::Clear(gl::COLOR_BUFFER_BIT);
             gl::DrawArrays(gl::TRIANGLES, 0, 3);
         }
-
-        window.swap_buffers().unwrap();
-
-        if let Event::Closed = event {
-            break;
-        }
     }
+}
 
-    unsafe {
-        // And let's not forget to cleanup after ourselves.
-        gl::DeleteProgram(program);
-        gl::DeleteBuffers(1, &vbo);
-        gl::DeleteVertexArrays(1, &vao);
+impl Drop for Scene {
+    fn drop(&mut self) {
+        unsafe {
+            // And let's not forget to cleanup after ourselves.
+            gl::DeleteProgram(self.program);
+            gl::DeleteVertexArrays(1, &self.vao);
+        }
     }
 }
 
-fn create_window(title: &str) -> Window {
-    use glutin::{Api, GlProfile, GlRequest, WindowBuilder};
-
-    let window = WindowBuilder::new()
-        .with_gl(GlRequest::Specific(Api::OpenGl, (3, 3)))
-        .with_gl_profile(GlProfile::Core)
-        .with_title(title)
-        .build()
-        .unwrap();
+// Runs the scene in a window. The scene needs the context that `app::run` creates, so it's only
+// created in `init`.
+struct HelloTriangle {
+    scene: Option<Scene>,
+}
 
-    unsafe { window.make_current().unwrap() };
+impl App for HelloTriangle {
+    fn init(&mut self, _window: &Window) {
+        self.scene = Some(Scene::new());
+    }
 
-    gl::load_with(|symbol| window.get_proc_address(symbol) as *const _);
+    fn render(&mut self) {
+        if let Some(ref scene) = self.scene {
+            scene.draw();
+        }
+    }
+}
 
-    window
+fn main() {
+    // We already know how to create a window, so let's leave that and the main loop to `app::run`.
+    // The triangle never changes, so it only needs to be redrawn when something happens to the
+    // window.
+    app::run(HelloTriangle { scene: None }, Config {
+        redraw: Redraw::OnDemand,
+        screenshots: Some(ScreenshotTrigger::new("02_hello_triangle")),
+        ..Config::new("Hello Triangle")
+    });
 }
 
 fn compile_shader(src: &str, shader_type: GLenum) -> Result<GLuint, String> {
@@ -240,26 +269,3 @@ fn link_program(vertex_shader_id: GLuint, fragment_shader_id: GLuint) -> Result<
         }
     }
 }
-
-fn create_vbo(vertices: &[GLfloat]) -> GLuint {
-    unsafe {
-        // 1. Create a buffer.
-        let mut vbo = 0;
-        gl::GenBuffers(1, &mut vbo);
-
-        // 2. Bind the buffer as a vertex buffer.
-        gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
-
-        // 3. Copy the vertices into the buffer.
-        gl::BufferData(
-            gl::ARRAY_BUFFER,
-            mem::size_of_val(vertices) as GLsizeiptr, // Byte size of the vertex data.
-            vertices.as_ptr() as *const GLvoid,        // Pointer to the vertex data to copy.
-            gl::STATIC_DRAW // Expected usage pattern for this data. In this example, the data
-                            // doesn't change and is used for drawing.
-        );
-
-        // Return the VBO handle
-        vbo
-    }
-}
@@ -4,8 +4,11 @@ extern crate gl;
 use std::mem;
 use std::ptr;
 
-use lgl::{create_window, Event};
-use lgl::program::{SourceCompiler, ShaderType};
+use lgl::Window;
+use lgl::app::{self, App, Config, Redraw};
+use lgl::buffer::{Buffer, BufferTarget, BufferUsage};
+use lgl::program::{Program, SourceCompiler, ShaderType};
+use lgl::screenshot::ScreenshotTrigger;
 
 use gl::types::*;
 
@@ -35,94 +38,112 @@ void main() {
 }
 "#;
 
-fn main() {
-    let window = create_window("Hello Triangle");
-
-    let program = SourceCompiler::compile(&[
-        (ShaderType::Vertex, VERTEX_SHADER_SRC),
-        (ShaderType::Fragment, FRAGMENT_SHADER_SRC),
-    ]).unwrap();
-
-    // Next, we'll upload the triangle vertices to the GPU, where they'll be processed by the program
-    // we just linked.
-    //
-    // But before we do that, we'll create a Vertex Array Object. This will let us store vertex
-    // attribute pointer configuration so that we only have to do it once. Whenever we use the VBO
-    // again, we only have to make sure the corresponding VAO is also bound for the same
-    // configuration to be used.
-
-    let mut vao = 0;
-    unsafe {
-        // Create and bind a VAO
-        gl::GenVertexArrays(1, &mut vao);
-        gl::BindVertexArray(vao);
-    }
+// Everything we need to draw a frame. Keeping it apart from the window lets the golden-image tests
+// (see `tests/golden.rs`) draw the same thing without one.
+pub struct Scene {
+    _program: Program,
+    vao: GLuint,
+    _vbo: Buffer<GLfloat>,
+}
 
-    // Create a Vertex Buffer Object and upload the vertices to the GPU.
-    let vbo = create_vbo(&VERTICES);
-
-    unsafe {
-        // And now, we configure the vertex attributes. This instructs the vertex shader how to
-        // interpret the vertex data.
-        let stride = (3 * mem::size_of::<GLfloat>()) as GLsizei;
-
-        gl::VertexAttribPointer(
-            0, // The index of the generic vertex attribute to be modified.
-            3, // The number of components per generic vertex attribute. vec3, in this case.
-            gl::FLOAT,  // The data type of each component in the array
-            gl::FALSE,  // Whether the data values should be normalized.
-            stride,     // The byte offset between consecutive generic vertex attributes.
-                        // We could have passed 0, which would mean the data is tightly packed.
-            ptr::null() // Offset of the first component of the first generic vertex attribute.
-                        // NULL means 0.
-        );
-
-        gl::EnableVertexAttribArray(0);
-    }
+impl Scene {
+    pub fn new() -> Scene {
+        let program = SourceCompiler::compile(&[
+            (ShaderType::Vertex, VERTEX_SHADER_SRC),
+            (ShaderType::Fragment, FRAGMENT_SHADER_SRC),
+        ]).unwrap();
+
+        // Next, we'll upload the triangle vertices to the GPU, where they'll be processed by the
+        // program we just linked.
+        //
+        // But before we do that, we'll create a Vertex Array Object. This will let us store vertex
+        // attribute pointer configuration so that we only have to do it once. Whenever we use the
+        // VBO again, we only have to make sure the corresponding VAO is also bound for the same
+        // configuration to be used.
+
+        let mut vao = 0;
+        unsafe {
+            // Create and bind a VAO
+            gl::GenVertexArrays(1, &mut vao);
+            gl::BindVertexArray(vao);
+        }
+
+        // Create a Vertex Buffer Object and upload the vertices to the GPU. `StaticDraw` is the
+        // expected usage pattern for this data: it doesn't change, and is used for drawing.
+        let vbo = Buffer::new(BufferTarget::Vertex, &VERTICES, BufferUsage::StaticDraw);
 
-    program.activate();
+        // The vertex attributes configured below read from the vertex buffer that is bound.
+        vbo.bind();
+
+        unsafe {
+            // And now, we configure the vertex attributes. This instructs the vertex shader how to
+            // interpret the vertex data.
+            let stride = (3 * mem::size_of::<GLfloat>()) as GLsizei;
+
+            gl::VertexAttribPointer(
+                0, // The index of the generic vertex attribute to be modified.
+                3, // The number of components per generic vertex attribute. vec3, in this case.
+                gl::FLOAT,  // The data type of each component in the array
+                gl::FALSE,  // Whether the data values should be normalized.
+                stride,     // The byte offset between consecutive generic vertex attributes.
+                            // We could have passed 0, which would mean the data is tightly packed.
+                ptr::null() // Offset of the first component of the first generic vertex attribute.
+                            // NULL means 0.
+            );
+
+            gl::EnableVertexAttribArray(0);
+        }
+
+        program.activate();
+
+        Scene {
+            _program: program,
+            vao,
+            _vbo: vbo,
+        }
+    }
 
-    for event in window.wait_events() {
+    pub fn draw(&self) {
         unsafe {
             // Lastly, we draw the object.
             gl::ClearColor(0.3, 0.3, 0.3, 1.0);
             gl::Clear(gl::COLOR_BUFFER_BIT);
             gl::DrawArrays(gl::TRIANGLES, 0, 3);
         }
+    }
+}
 
-        window.swap_buffers().unwrap();
-
-        if let Event::Closed = event {
-            break;
+impl Drop for Scene {
+    fn drop(&mut self) {
+        // The program deletes itself when dropped, but the buffer and the VAO are ours to delete.
+        unsafe {
+            gl::DeleteVertexArrays(1, &self.vao);
         }
     }
+}
 
-    unsafe {
-        // And let's not forget to cleanup after ourselves.
-        gl::DeleteBuffers(1, &vbo);
-        gl::DeleteVertexArrays(1, &vao);
-    }
+// Runs the scene in a window. The scene needs the context that `app::run` creates, so it's only
+// created in `init`.
+struct HelloTriangle {
+    scene: Option<Scene>,
 }
 
-fn create_vbo(vertices: &[GLfloat]) -> GLuint {
-    unsafe {
-        // 1. Create a buffer.
-        let mut vbo = 0;
-        gl::GenBuffers(1, &mut vbo);
-
-        // 2. Bind the buffer as a vertex buffer.
-        gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
-
-        // 3. Copy the vertices into the buffer.
-        gl::BufferData(
-            gl::ARRAY_BUFFER,
-            mem::size_of_val(vertices) as GLsizeiptr, // Byte size of the vertex data.
-            vertices.as_ptr() as *const GLvoid,        // Pointer to the vertex data to copy.
-            gl::STATIC_DRAW // Expected usage pattern for this data. In this example, the data
-                            // doesn't change and is used for drawing.
-        );
-
-        // Return the VBO handle
-        vbo
+impl App for HelloTriangle {
+    fn init(&mut self, _window: &Window) {
+        self.scene = Some(Scene::new());
     }
+
+    fn render(&mut self) {
+        if let Some(ref scene) = self.scene {
+            scene.draw();
+        }
+    }
+}
+
+fn main() {
+    app::run(HelloTriangle { scene: None }, Config {
+        redraw: Redraw::OnDemand,
+        screenshots: Some(ScreenshotTrigger::new("02b_hello_triangle")),
+        ..Config::new("Hello Triangle")
+    });
 }
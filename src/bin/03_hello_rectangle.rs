@@ -1,10 +1,14 @@
-extern crate glutin;
+extern crate learn_opengl as lgl;
 extern crate gl;
 
 use std::mem;
 use std::ptr;
 
-use glutin::{Window, Event};
+use lgl::Window;
+use lgl::app::{self, App, Config, Redraw};
+use lgl::buffer::{Buffer, BufferTarget, BufferUsage};
+use lgl::render_state::{PolygonMode, RenderState};
+use lgl::screenshot::ScreenshotTrigger;
 
 use gl::types::*;
 
@@ -40,46 +44,72 @@ void main() {
 }
 "#;
 
-fn main() {
-    // This example is similar to the "Hello Triangle" one, but we use an Element Buffer Object to
-    // render a Rectangle without needing to upload the shared vertices.
+// Everything we need to draw a frame. Keeping it apart from the window lets the golden-image tests
+// (see `tests/golden.rs`) draw the same thing without one.
+pub struct Scene {
+    program: GLuint,
+    vao: GLuint,
+    _vbo: Buffer<GLfloat>,
+    _ebo: Buffer<GLuint>,
+    render_state: RenderState,
+}
 
-    let window  = create_window("Hello Rectangle");
-    let program = create_program(VERTEX_SHADER_SRC, FRAGMENT_SHADER_SRC);
+impl Scene {
+    pub fn new() -> Scene {
+        let program = create_program(VERTEX_SHADER_SRC, FRAGMENT_SHADER_SRC);
 
-    let mut vao = 0;
-    unsafe {
-        gl::GenVertexArrays(1, &mut vao);
-        gl::BindVertexArray(vao);
-    }
+        let mut vao = 0;
+        unsafe {
+            gl::GenVertexArrays(1, &mut vao);
+            gl::BindVertexArray(vao);
+        }
 
-    let vbo = create_vbo(&VERTICES);
-    let ebo = create_ebo(&INDICES);
+        let vbo = Buffer::new(BufferTarget::Vertex, &VERTICES, BufferUsage::StaticDraw);
+        vbo.bind();
 
-    unsafe {
-        let stride = (3 * mem::size_of::<GLfloat>()) as GLsizei;
-        gl::VertexAttribPointer(0, 3, gl::FLOAT, gl::FALSE, stride, ptr::null());
-        gl::EnableVertexAttribArray(0);
-
-        // Unbind the current VAO.
-        //
-        // While this ins not actually needed for this demo, it would prevent us from accidentally
-        // messing around the VAO.
-        gl::BindVertexArray(0)
+        // The indices go in an Element Buffer Object. Binding it while the VAO is bound attaches it
+        // to the VAO, so the draw below only has to bind the VAO again.
+        let ebo = Buffer::new(BufferTarget::Index, &INDICES, BufferUsage::StaticDraw);
+        ebo.bind();
+
+        unsafe {
+            let stride = (3 * mem::size_of::<GLfloat>()) as GLsizei;
+            gl::VertexAttribPointer(0, 3, gl::FLOAT, gl::FALSE, stride, ptr::null());
+            gl::EnableVertexAttribArray(0);
+
+            // Unbind the current VAO.
+            //
+            // While this ins not actually needed for this demo, it would prevent us from
+            // accidentally messing around the VAO.
+            gl::BindVertexArray(0)
+        }
+
+        // Change this to `PolygonMode::Line` to activate wireframe mode.
+        let render_state = RenderState {
+            polygon_mode: PolygonMode::Fill,
+            ..RenderState::default()
+        };
+
+        Scene {
+            program,
+            vao,
+            _vbo: vbo,
+            _ebo: ebo,
+            render_state,
+        }
     }
 
-    // Uncomment this line to activate wireframe mode.
-    // unsafe { gl::PolygonMode(gl::FRONT_AND_BACK, gl::LINE); }
+    pub fn draw(&self) {
+        self.render_state.apply();
 
-    for event in window.wait_events() {
         unsafe {
-            gl::UseProgram(program);
+            gl::UseProgram(self.program);
 
             gl::ClearColor(0.3, 0.3, 0.3, 1.0);
             gl::Clear(gl::COLOR_BUFFER_BIT);
 
             // Rebind the VAO for this draw.
-            gl::BindVertexArray(vao);
+            gl::BindVertexArray(self.vao);
 
             // Instead of gl::DrawArrays, we use gl::DrawElements.
             gl::DrawElements(
@@ -92,37 +122,45 @@ fn main() {
             // Unbind VAO.
             gl::BindVertexArray(0);
         }
-
-        window.swap_buffers().unwrap();
-
-        if let Event::Closed = event {
-            break;
-        }
     }
+}
 
-    unsafe {
-        gl::DeleteProgram(program);
-        gl::DeleteBuffers(1, &vbo);
-        gl::DeleteBuffers(1, &ebo);
-        gl::DeleteVertexArrays(1, &vao);
+impl Drop for Scene {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteProgram(self.program);
+            gl::DeleteVertexArrays(1, &self.vao);
+        }
     }
 }
 
-fn create_window(title: &str) -> Window {
-    use glutin::{Api, GlProfile, GlRequest, WindowBuilder};
+// Runs the scene in a window. The scene needs the context that `app::run` creates, so it's only
+// created in `init`.
+struct HelloRectangle {
+    scene: Option<Scene>,
+}
 
-    let window = WindowBuilder::new()
-        .with_gl(GlRequest::Specific(Api::OpenGl, (3, 3)))
-        .with_gl_profile(GlProfile::Core)
-        .with_title(title)
-        .build()
-        .unwrap();
+impl App for HelloRectangle {
+    fn init(&mut self, _window: &Window) {
+        self.scene = Some(Scene::new());
+    }
 
-    unsafe { window.make_current().unwrap() };
+    fn render(&mut self) {
+        if let Some(ref scene) = self.scene {
+            scene.draw();
+        }
+    }
+}
 
-    gl::load_with(|symbol| window.get_proc_address(symbol) as *const _);
+fn main() {
+    // This example is similar to the "Hello Triangle" one, but we use an Element Buffer Object to
+    // render a Rectangle without needing to upload the shared vertices.
 
-    window
+    app::run(HelloRectangle { scene: None }, Config {
+        redraw: Redraw::OnDemand,
+        screenshots: Some(ScreenshotTrigger::new("03_hello_rectangle")),
+        ..Config::new("Hello Rectangle")
+    });
 }
 
 fn create_program(vertex_shader: &str, fragment_shader: &str) -> GLuint {
@@ -201,41 +239,3 @@ fn link_program(vertex_shader_id: GLuint, fragment_shader_id: GLuint) -> Result<
         }
     }
 }
-
-fn create_vbo(vertices: &[GLfloat]) -> GLuint {
-    unsafe {
-        let mut vbo = 0;
-        gl::GenBuffers(1, &mut vbo);
-        gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
-        gl::BufferData(
-            gl::ARRAY_BUFFER,
-            mem::size_of_val(vertices) as GLsizeiptr,
-            vertices.as_ptr() as *const GLvoid,
-            gl::STATIC_DRAW
-        );
-
-        vbo
-    }
-}
-
-fn create_ebo(indices: &[GLuint]) -> GLuint {
-    unsafe {
-        // 1. Create the EBO.
-        let mut ebo = 0;
-        gl::GenBuffers(1, &mut ebo);
-
-        // 2. Bind the buffer as an Element Buffer.
-        gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, ebo);
-
-        // 3. Copy the indices into the buffer.
-        gl::BufferData(
-            gl::ELEMENT_ARRAY_BUFFER,
-            mem::size_of_val(indices) as GLsizeiptr,
-            indices.as_ptr() as *const GLvoid,
-            gl::STATIC_DRAW
-        );
-
-        // Return the EBO handle
-        ebo
-    }
-}
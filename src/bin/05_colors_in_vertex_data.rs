@@ -1,12 +1,16 @@
-extern crate glutin;
+extern crate learn_opengl as lgl;
 extern crate gl;
 
 use std::mem;
 use std::ptr;
 
-use glutin::{Window, Event};
 use gl::types::*;
 
+use lgl::Window;
+use lgl::app::{self, App, Config, Redraw};
+use lgl::buffer::{Buffer, BufferTarget, BufferUsage};
+use lgl::screenshot::ScreenshotTrigger;
+
 const VERTEX_SHADER_SRC: &'static str = r#"
 #version 330 core
 
@@ -41,78 +45,100 @@ const VERTEX_DATA: [GLfloat; 3 * VALUES_PER_VERTEX] = [
      0.0,  0.5, 0.0,    0.0, 0.0, 1.0, // Top
 ];
 
-fn main() {
-    // In this demo, we'll upload a different color for each vertex in the Vertex Buffer Object.
-    // The vertex shader has changed to include a new input variable (color) and an output variable
-    // (our_color), so that we can pass the color to the fragment shader.
+// Everything we need to draw a frame. Keeping it apart from the window lets the golden-image tests
+// (see `tests/golden.rs`) draw the same thing without one.
+pub struct Scene {
+    program: GLuint,
+    vao: GLuint,
+    _vbo: Buffer<GLfloat>,
+}
 
-    let window  = create_window("Colors in vertex data");
-    let program = create_program(VERTEX_SHADER_SRC, FRAGMENT_SHADER_SRC);
+impl Scene {
+    pub fn new() -> Scene {
+        let program = create_program(VERTEX_SHADER_SRC, FRAGMENT_SHADER_SRC);
 
-    let mut vao = 0;
-    unsafe {
-        gl::GenVertexArrays(1, &mut vao);
-        gl::BindVertexArray(vao);
-    }
+        let mut vao = 0;
+        unsafe {
+            gl::GenVertexArrays(1, &mut vao);
+            gl::BindVertexArray(vao);
+        }
 
-    let vbo = create_vbo(&VERTEX_DATA);
+        let vbo = Buffer::new(BufferTarget::Vertex, &VERTEX_DATA, BufferUsage::StaticDraw);
+        vbo.bind();
 
-    unsafe {
-        let gl_float_size = mem::size_of::<GLfloat>();
-        let stride = (VALUES_PER_VERTEX * gl_float_size) as GLsizei;
+        unsafe {
+            let gl_float_size = mem::size_of::<GLfloat>();
+            let stride = (VALUES_PER_VERTEX * gl_float_size) as GLsizei;
 
-        gl::VertexAttribPointer(0, 3, gl::FLOAT, gl::FALSE, stride, 0 as *const GLvoid);
-        gl::EnableVertexAttribArray(0);
+            gl::VertexAttribPointer(0, 3, gl::FLOAT, gl::FALSE, stride, 0 as *const GLvoid);
+            gl::EnableVertexAttribArray(0);
 
-        // As with the position vertex attribute, we need to configure the color attribute. We
-        // specified the attribute location manually, so we don't need to query it.
-        gl::VertexAttribPointer(1, 3, gl::FLOAT, gl::FALSE, stride, (3 * gl_float_size) as *const GLvoid);
-        gl::EnableVertexAttribArray(1);
+            // As with the position vertex attribute, we need to configure the color attribute. We
+            // specified the attribute location manually, so we don't need to query it.
+            gl::VertexAttribPointer(1, 3, gl::FLOAT, gl::FALSE, stride, (3 * gl_float_size) as *const GLvoid);
+            gl::EnableVertexAttribArray(1);
 
-        gl::BindVertexArray(0)
-    }
+            gl::BindVertexArray(0)
+        }
 
-    for event in window.wait_events() {
-        if let Event::Closed = event {
-            break;
+        Scene {
+            program,
+            vao,
+            _vbo: vbo,
         }
+    }
 
+    pub fn draw(&self) {
         unsafe {
-            gl::UseProgram(program);
+            gl::UseProgram(self.program);
 
             gl::ClearColor(0.3, 0.3, 0.3, 1.0);
             gl::Clear(gl::COLOR_BUFFER_BIT);
 
-            gl::BindVertexArray(vao);
+            gl::BindVertexArray(self.vao);
             gl::DrawArrays(gl::TRIANGLES, 0, 3);
             gl::BindVertexArray(0);
         }
-
-        window.swap_buffers().unwrap();
     }
+}
 
-    unsafe {
-        gl::DeleteProgram(program);
-        gl::DeleteBuffers(1, &vbo);
-        gl::DeleteVertexArrays(1, &vao);
+impl Drop for Scene {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteProgram(self.program);
+            gl::DeleteVertexArrays(1, &self.vao);
+        }
     }
 }
 
-fn create_window(title: &str) -> Window {
-    use glutin::{Api, GlProfile, GlRequest, WindowBuilder};
+// Runs the scene in a window. The scene needs the context that `app::run` creates, so it's only
+// created in `init`.
+struct ColorsInVertexData {
+    scene: Option<Scene>,
+}
 
-    let window = WindowBuilder::new()
-        .with_gl(GlRequest::Specific(Api::OpenGl, (3, 3)))
-        .with_gl_profile(GlProfile::Core)
-        .with_title(title)
-        .build()
-        .unwrap();
+impl App for ColorsInVertexData {
+    fn init(&mut self, _window: &Window) {
+        self.scene = Some(Scene::new());
+    }
 
-    unsafe { window.make_current().unwrap() };
+    fn render(&mut self) {
+        if let Some(ref scene) = self.scene {
+            scene.draw();
+        }
+    }
+}
 
-    gl::load_with(|symbol| window.get_proc_address(symbol) as *const _);
+fn main() {
+    // In this demo, we'll upload a different color for each vertex in the Vertex Buffer Object.
+    // The vertex shader has changed to include a new input variable (color) and an output variable
+    // (our_color), so that we can pass the color to the fragment shader.
 
-    window
+    app::run(ColorsInVertexData { scene: None }, Config {
+        redraw: Redraw::OnDemand,
+        screenshots: Some(ScreenshotTrigger::new("05_colors_in_vertex_data")),
+        ..Config::new("Colors in vertex data")
+    });
 }
 
 fn create_program(vertex_shader: &str, fragment_shader: &str) -> GLuint {
@@ -191,19 +217,3 @@ fn link_program(vertex_shader_id: GLuint, fragment_shader_id: GLuint) -> Result<
         }
     }
 }
-
-fn create_vbo(vertices: &[GLfloat]) -> GLuint {
-    unsafe {
-        let mut vbo = 0;
-        gl::GenBuffers(1, &mut vbo);
-        gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
-        gl::BufferData(
-            gl::ARRAY_BUFFER,
-            mem::size_of_val(vertices) as GLsizeiptr,
-            vertices.as_ptr() as *const GLvoid,
-            gl::STATIC_DRAW
-        );
-
-        vbo
-    }
-}
@@ -0,0 +1,137 @@
+use gl;
+use gl::types::*;
+
+use std::ffi;
+use std::fs;
+use std::io;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+use program::{Program, ShaderType};
+
+// Compiling and linking is by far the slowest part of creating a program. Drivers can hand us the
+// linked program as an opaque binary, which we store on disk and load on later runs instead.
+//
+// Binaries are only valid for the driver that produced them, so the cache key covers the driver's
+// vendor, renderer and version strings as well as the shader sources. Even then, a driver may
+// reject a binary, in which case we simply compile from source and overwrite the cache entry. When
+// the driver doesn't support any binary format, the cache is bypassed altogether.
+//
+// Each entry is a file named after the key, holding the binary format as a little-endian `u32`
+// followed by the binary itself.
+pub struct ProgramCache {
+    dir: PathBuf,
+}
+
+impl ProgramCache {
+    pub fn new<P: AsRef<Path>>(dir: P) -> ProgramCache {
+        ProgramCache {
+            dir: dir.as_ref().to_path_buf(),
+        }
+    }
+
+    pub fn get_or_compile<F, E>(&self, shader_sources: &[(ShaderType, &str)], compile: F) -> Result<Program, E>
+        where F: FnOnce() -> Result<Program, E>
+    {
+        if !binaries_supported() {
+            return compile();
+        }
+
+        let path = self.entry_path(shader_sources);
+
+        if let Some(program) = self.load(&path) {
+            return Ok(program);
+        }
+
+        let program = compile()?;
+
+        // The cache is only an optimization, so failing to write to it is not an error.
+        let _ = self.store(&path, &program);
+
+        Ok(program)
+    }
+
+    pub fn clear(&self) -> io::Result<()> {
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+
+            if path.extension().map_or(false, |ext| ext == "bin") {
+                fs::remove_file(path)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn entry_path(&self, shader_sources: &[(ShaderType, &str)]) -> PathBuf {
+        // `DefaultHasher` isn't guaranteed to be stable across Rust releases. That's fine here, as
+        // a different hash only means a cache miss.
+        let mut hasher = DefaultHasher::new();
+
+        for name in &[gl::VENDOR, gl::RENDERER, gl::VERSION] {
+            gl_string(*name).hash(&mut hasher);
+        }
+
+        for &(ty, source) in shader_sources {
+            GLenum::from(ty).hash(&mut hasher);
+            source.hash(&mut hasher);
+        }
+
+        self.dir.join(format!("{:016x}.bin", hasher.finish()))
+    }
+
+    fn load(&self, path: &Path) -> Option<Program> {
+        let mut data = Vec::new();
+        fs::File::open(path).and_then(|mut f| f.read_to_end(&mut data)).ok()?;
+
+        if data.len() < 4 {
+            return None;
+        }
+
+        let format = data[..4].iter().rev().fold(0, |format, &b| (format << 8) | b as GLenum);
+
+        Program::from_binary(format, &data[4..])
+    }
+
+    fn store(&self, path: &Path, program: &Program) -> io::Result<()> {
+        let (format, binary) = match program.binary() {
+            Some(binary) => binary,
+            None => return Ok(()),
+        };
+
+        fs::create_dir_all(&self.dir)?;
+
+        let format_bytes = [format as u8, (format >> 8) as u8, (format >> 16) as u8, (format >> 24) as u8];
+
+        let mut file = fs::File::create(path)?;
+        file.write_all(&format_bytes)?;
+        file.write_all(&binary)
+    }
+}
+
+// The functions are core since OpenGL 4.1, but a driver can provide them without supporting a
+// single binary format, as older Mesa versions did.
+fn binaries_supported() -> bool {
+    if !gl::ProgramBinary::is_loaded() || !gl::GetProgramBinary::is_loaded() {
+        return false;
+    }
+
+    let mut formats = 0;
+    unsafe { gl::GetIntegerv(gl::NUM_PROGRAM_BINARY_FORMATS, &mut formats); }
+
+    formats > 0
+}
+
+fn gl_string(name: GLenum) -> String {
+    unsafe {
+        let ptr = gl::GetString(name);
+
+        if ptr.is_null() {
+            String::new()
+        } else {
+            ffi::CStr::from_ptr(ptr as *const _).to_string_lossy().into_owned()
+        }
+    }
+}
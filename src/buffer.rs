@@ -0,0 +1,189 @@
+use gl;
+use gl::types::*;
+
+use std::mem;
+use std::ptr;
+use std::marker::PhantomData;
+
+use gl_object::{GlObject, Handle};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BufferTarget {
+    Vertex,
+    Index,
+    Uniform,
+    Storage,
+}
+
+impl From<BufferTarget> for GLenum {
+    fn from(target: BufferTarget) -> Self {
+        match target {
+            BufferTarget::Vertex  => gl::ARRAY_BUFFER,
+            BufferTarget::Index   => gl::ELEMENT_ARRAY_BUFFER,
+            BufferTarget::Uniform => gl::UNIFORM_BUFFER,
+            BufferTarget::Storage => gl::SHADER_STORAGE_BUFFER,
+        }
+    }
+}
+
+// A hint of how often the data will change (static, dynamic or stream) and what it's used for
+// (draw, read or copy). Drivers use it to decide where to keep the buffer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BufferUsage {
+    StaticDraw,
+    StaticRead,
+    StaticCopy,
+    DynamicDraw,
+    DynamicRead,
+    DynamicCopy,
+    StreamDraw,
+    StreamRead,
+    StreamCopy,
+}
+
+impl From<BufferUsage> for GLenum {
+    fn from(usage: BufferUsage) -> Self {
+        match usage {
+            BufferUsage::StaticDraw  => gl::STATIC_DRAW,
+            BufferUsage::StaticRead  => gl::STATIC_READ,
+            BufferUsage::StaticCopy  => gl::STATIC_COPY,
+            BufferUsage::DynamicDraw => gl::DYNAMIC_DRAW,
+            BufferUsage::DynamicRead => gl::DYNAMIC_READ,
+            BufferUsage::DynamicCopy => gl::DYNAMIC_COPY,
+            BufferUsage::StreamDraw  => gl::STREAM_DRAW,
+            BufferUsage::StreamRead  => gl::STREAM_READ,
+            BufferUsage::StreamCopy  => gl::STREAM_COPY,
+        }
+    }
+}
+
+// A buffer holding `len` values of type `T`. Offsets and lengths are counted in values, not bytes.
+pub struct Buffer<T: Copy> {
+    id: Handle,
+    target: BufferTarget,
+    usage: BufferUsage,
+    len: usize,
+    _marker: PhantomData<T>,
+}
+
+impl<T: Copy> Drop for Buffer<T> {
+    fn drop(&mut self) {
+        unsafe { gl::DeleteBuffers(1, &self.id); }
+    }
+}
+
+impl<T: Copy> GlObject for Buffer<T> {
+    #[inline]
+    fn id(&self) -> Handle {
+        self.id
+    }
+}
+
+impl<T: Copy> Buffer<T> {
+    pub fn new(target: BufferTarget, data: &[T], usage: BufferUsage) -> Buffer<T> {
+        let mut buffer = Buffer::create(target, usage);
+        buffer.allocate(data.len(), data.as_ptr());
+
+        buffer
+    }
+
+    // Allocates room for `len` values, without initializing them.
+    pub fn with_len(target: BufferTarget, len: usize, usage: BufferUsage) -> Buffer<T> {
+        let mut buffer = Buffer::create(target, usage);
+        buffer.allocate(len, ptr::null());
+
+        buffer
+    }
+
+    fn create(target: BufferTarget, usage: BufferUsage) -> Buffer<T> {
+        let mut id = 0;
+        unsafe { gl::GenBuffers(1, &mut id); }
+
+        Buffer {
+            id,
+            target,
+            usage,
+            len: 0,
+            _marker: PhantomData,
+        }
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    #[inline]
+    pub fn target(&self) -> BufferTarget {
+        self.target
+    }
+
+    #[inline]
+    pub fn usage(&self) -> BufferUsage {
+        self.usage
+    }
+
+    // Note that binding an index buffer while a vertex array is bound attaches it to that vertex
+    // array.
+    pub fn bind(&self) {
+        unsafe { gl::BindBuffer(self.target.into(), self.id); }
+    }
+
+    pub fn unbind(&self) {
+        unsafe { gl::BindBuffer(self.target.into(), 0); }
+    }
+
+    // Binds a uniform or storage buffer to an indexed binding point, eg. the one a uniform block
+    // was assigned to with `layout (binding = N)`.
+    pub fn bind_base(&self, index: GLuint) {
+        unsafe { gl::BindBufferBase(self.target.into(), index, self.id); }
+    }
+
+    // Overwrites part of the buffer, starting at `offset`. Panics if the data doesn't fit.
+    pub fn sub_data(&self, offset: usize, data: &[T]) {
+        assert!(offset + data.len() <= self.len,
+            "sub_data out of range: offset {} + length {} > buffer length {}", offset, data.len(), self.len);
+
+        unsafe {
+            gl::BindBuffer(gl::COPY_WRITE_BUFFER, self.id);
+            gl::BufferSubData(
+                gl::COPY_WRITE_BUFFER,
+                (offset * mem::size_of::<T>()) as GLintptr,
+                mem::size_of_val(data) as GLsizeiptr,
+                data.as_ptr() as *const GLvoid
+            );
+            gl::BindBuffer(gl::COPY_WRITE_BUFFER, 0);
+        }
+    }
+
+    // Replaces the whole contents of the buffer, resizing it if needed.
+    pub fn update(&mut self, data: &[T]) {
+        if data.len() == self.len {
+            self.sub_data(0, data);
+        } else {
+            self.allocate(data.len(), data.as_ptr());
+        }
+    }
+
+    // (Re)allocates the buffer's storage. We upload through the `COPY_WRITE_BUFFER` target, which
+    // has no special meaning for drawing, so we don't disturb the current vertex array's bindings.
+    fn allocate(&mut self, len: usize, data: *const T) {
+        unsafe {
+            gl::BindBuffer(gl::COPY_WRITE_BUFFER, self.id);
+            gl::BufferData(
+                gl::COPY_WRITE_BUFFER,
+                (len * mem::size_of::<T>()) as GLsizeiptr,
+                data as *const GLvoid,
+                self.usage.into()
+            );
+            gl::BindBuffer(gl::COPY_WRITE_BUFFER, 0);
+        }
+
+        self.len = len;
+    }
+}
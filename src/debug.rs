@@ -1,17 +1,86 @@
 use gl;
-use gl::types::GLuint;
+use gl::types::*;
 
 use std::fmt;
+use std::panic;
+use std::process;
+use std::ptr;
+use std::slice;
+use std::cell::RefCell;
 use std::error::Error;
 
+// These macros check for OpenGL errors after a call. `glGetError` only returns one error at a time,
+// and several can be queued up, so each of them drains all pending errors.
+//
+// - `check_gl_error!` prints the errors to stdout.
+// - `gl_result!` returns a `Result<(), GlErrors>`, which can be used with `?`.
+// - `assert_no_gl_error!` panics with the errors.
+//
+// All of them take an optional label, which is included in the output along with the file and
+// line. They're enabled in debug builds, and compile to no-ops in release builds so those don't pay
+// for the checks. The `check-gl-error`, `gl-result` and `assert-no-gl-error` features keep one of
+// them enabled in release builds too, and `gl-error-checks` keeps all three.
+
+#[cfg(any(debug_assertions, feature = "check-gl-error"))]
 #[macro_export]
 macro_rules! check_gl_error {
     () => {
-        match $crate::debug::GlError::check() {
-            Some(error) => println!("{}:{}: {}", file!(), line!(), error),
-            _ => (),
+        for error in $crate::debug::GlError::drain() {
+            println!("{}:{}: {}", file!(), line!(), error);
         }
-    }
+    };
+    ($label:expr) => {
+        for error in $crate::debug::GlError::drain() {
+            println!("{}:{}: {}: {}", file!(), line!(), $label, error);
+        }
+    };
+}
+
+#[cfg(any(debug_assertions, feature = "gl-result"))]
+#[macro_export]
+macro_rules! gl_result {
+    () => {
+        $crate::debug::GlErrors::check(file!(), line!(), None)
+    };
+    ($label:expr) => {
+        $crate::debug::GlErrors::check(file!(), line!(), Some($label))
+    };
+}
+
+#[cfg(any(debug_assertions, feature = "assert-no-gl-error"))]
+#[macro_export]
+macro_rules! assert_no_gl_error {
+    () => {
+        if let Err(errors) = $crate::debug::GlErrors::check(file!(), line!(), None) {
+            panic!("{}", errors);
+        }
+    };
+    ($label:expr) => {
+        if let Err(errors) = $crate::debug::GlErrors::check(file!(), line!(), Some($label)) {
+            panic!("{}", errors);
+        }
+    };
+}
+
+#[cfg(not(any(debug_assertions, feature = "check-gl-error")))]
+#[macro_export]
+macro_rules! check_gl_error {
+    () => { () };
+    ($label:expr) => { () };
+}
+
+#[cfg(not(any(debug_assertions, feature = "gl-result")))]
+#[macro_export]
+macro_rules! gl_result {
+    () => { Ok::<(), $crate::debug::GlErrors>(()) };
+    ($label:expr) => { Ok::<(), $crate::debug::GlErrors>(()) };
+}
+
+#[cfg(not(any(debug_assertions, feature = "assert-no-gl-error")))]
+#[macro_export]
+macro_rules! assert_no_gl_error {
+    () => { () };
+    ($label:expr) => { () };
 }
 
 #[derive(Debug)]
@@ -46,19 +115,93 @@ impl GlError {
 
         GlError::from_error_code(error_code)
     }
+
+    // Returns every pending error. Without a current context, some drivers keep returning an error
+    // forever, so we give up after a reasonable number of them.
+    pub fn drain() -> Vec<GlError> {
+        const MAX_ERRORS: usize = 32;
+
+        let mut errors = Vec::new();
+
+        while errors.len() < MAX_ERRORS {
+            match GlError::check() {
+                Some(error) => errors.push(error),
+                None        => break,
+            }
+        }
+
+        errors
+    }
+}
+
+#[derive(Debug)]
+pub struct GlErrors {
+    pub file: &'static str,
+    pub line: u32,
+    pub label: Option<String>,
+    pub errors: Vec<GlError>,
+}
+
+impl GlErrors {
+    pub fn check(file: &'static str, line: u32, label: Option<&str>) -> Result<(), GlErrors> {
+        let errors = GlError::drain();
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(GlErrors {
+                file,
+                line,
+                label: label.map(|l| l.to_string()),
+                errors,
+            })
+        }
+    }
+}
+
+impl fmt::Display for GlErrors {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}: ", self.file, self.line)?;
+
+        if let Some(ref label) = self.label {
+            write!(f, "{}: ", label)?;
+        }
+
+        for (i, error) in self.errors.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+
+            write!(f, "{}", error)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Error for GlErrors {
+    fn description(&self) -> &str {
+        "OpenGL errors"
+    }
 }
 
 impl fmt::Display for GlError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
-            GlError::Unrecognized(error_code) => write!(f, "{}: {}", self.description(), error_code),
-            _                                 => write!(f, "{}", self.description()),
+            GlError::Unrecognized(error_code) => write!(f, "{}: {}", self.message(), error_code),
+            _                                 => write!(f, "{}", self.message()),
         }
     }
 }
 
 impl Error for GlError {
     fn description(&self) -> &str {
+        self.message()
+    }
+}
+
+impl GlError {
+    fn message(&self) -> &'static str {
         match *self {
             GlError::InvalidEnum                 => "Invalid enum error",
             GlError::InvalidValue                => "Invalid value error",
@@ -71,3 +214,298 @@ impl Error for GlError {
         }
     }
 }
+
+// With KHR_debug (core since OpenGL 4.3), the driver can call us back with a message as soon as
+// something goes wrong, instead of us having to poll `glGetError`. Older drivers often have the
+// ARB_debug_output extension instead, which KHR_debug grew out of, and which we fall back to.
+// Drivers are only required to report messages in debug contexts, so this works best with a window
+// created by `create_window(title, true)`.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DebugSource {
+    Api,
+    WindowSystem,
+    ShaderCompiler,
+    ThirdParty,
+    Application,
+    Other,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DebugType {
+    Error,
+    DeprecatedBehavior,
+    UndefinedBehavior,
+    Portability,
+    Performance,
+    Marker,
+    PushGroup,
+    PopGroup,
+    Other,
+}
+
+// Ordered from least to most severe, so that filtering by a minimum severity is a comparison.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DebugSeverity {
+    Notification,
+    Low,
+    Medium,
+    High,
+}
+
+impl DebugSource {
+    fn from_gl(source: GLenum) -> DebugSource {
+        match source {
+            gl::DEBUG_SOURCE_API             => DebugSource::Api,
+            gl::DEBUG_SOURCE_WINDOW_SYSTEM   => DebugSource::WindowSystem,
+            gl::DEBUG_SOURCE_SHADER_COMPILER => DebugSource::ShaderCompiler,
+            gl::DEBUG_SOURCE_THIRD_PARTY     => DebugSource::ThirdParty,
+            gl::DEBUG_SOURCE_APPLICATION     => DebugSource::Application,
+            _                                => DebugSource::Other,
+        }
+    }
+}
+
+impl DebugType {
+    fn from_gl(ty: GLenum) -> DebugType {
+        match ty {
+            gl::DEBUG_TYPE_ERROR               => DebugType::Error,
+            gl::DEBUG_TYPE_DEPRECATED_BEHAVIOR => DebugType::DeprecatedBehavior,
+            gl::DEBUG_TYPE_UNDEFINED_BEHAVIOR  => DebugType::UndefinedBehavior,
+            gl::DEBUG_TYPE_PORTABILITY         => DebugType::Portability,
+            gl::DEBUG_TYPE_PERFORMANCE         => DebugType::Performance,
+            gl::DEBUG_TYPE_MARKER              => DebugType::Marker,
+            gl::DEBUG_TYPE_PUSH_GROUP          => DebugType::PushGroup,
+            gl::DEBUG_TYPE_POP_GROUP           => DebugType::PopGroup,
+            _                                  => DebugType::Other,
+        }
+    }
+}
+
+impl DebugSeverity {
+    fn from_gl(severity: GLenum) -> DebugSeverity {
+        match severity {
+            gl::DEBUG_SEVERITY_HIGH   => DebugSeverity::High,
+            gl::DEBUG_SEVERITY_MEDIUM => DebugSeverity::Medium,
+            gl::DEBUG_SEVERITY_LOW    => DebugSeverity::Low,
+            _                         => DebugSeverity::Notification,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct DebugMessage {
+    pub source: DebugSource,
+    pub ty: DebugType,
+    pub id: GLuint,
+    pub severity: DebugSeverity,
+    pub message: String,
+}
+
+impl fmt::Display for DebugMessage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "[{:?}] {:?} {:?} {}: {}", self.severity, self.source, self.ty, self.id, self.message)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct DebugOptions {
+    min_severity: DebugSeverity,
+    ignored_ids: Vec<GLuint>,
+    abort_on_error: bool,
+}
+
+impl DebugOptions {
+    pub fn new() -> DebugOptions {
+        DebugOptions {
+            min_severity: DebugSeverity::Notification,
+            ignored_ids: Vec::new(),
+            abort_on_error: false,
+        }
+    }
+
+    // Messages less severe than this are dropped.
+    pub fn min_severity(mut self, severity: DebugSeverity) -> DebugOptions {
+        self.min_severity = severity;
+        self
+    }
+
+    // Some drivers are very chatty, eg. NVIDIA reports every buffer allocation. Message ids are
+    // driver-specific, so this is the simplest way to silence a particular message.
+    pub fn ignore_id(mut self, id: GLuint) -> DebugOptions {
+        self.ignored_ids.push(id);
+        self
+    }
+
+    // Abort the process as soon as the driver reports a `DebugType::Error` message. The callback
+    // can't unwind back into the driver, so this is a `process::abort` rather than a panic that
+    // could be caught. The message is printed by the panic hook first, and as messages are
+    // delivered synchronously, the backtrace (with `RUST_BACKTRACE=1`) points at the GL call that
+    // caused the error.
+    pub fn abort_on_error(mut self, abort_on_error: bool) -> DebugOptions {
+        self.abort_on_error = abort_on_error;
+        self
+    }
+
+    fn accepts(&self, message: &DebugMessage) -> bool {
+        message.severity >= self.min_severity && !self.ignored_ids.contains(&message.id)
+    }
+}
+
+impl Default for DebugOptions {
+    fn default() -> DebugOptions {
+        DebugOptions::new()
+    }
+}
+
+struct DebugCallbackState {
+    options: DebugOptions,
+    callback: Box<dyn Fn(&DebugMessage)>,
+}
+
+// The driver only gives us back a raw pointer to the callback state, so we keep ownership of it
+// here. Messages are delivered synchronously, ie. on the thread that made the GL call, which is the
+// thread that owns the context.
+thread_local!(static CALLBACK_STATE: RefCell<Option<Box<DebugCallbackState>>> = const { RefCell::new(None) });
+
+// Routes driver messages to `callback`. Returns `false` if neither KHR_debug nor ARB_debug_output
+// is available.
+pub fn install_callback<F>(options: DebugOptions, callback: F) -> bool
+    where F: Fn(&DebugMessage) + 'static
+{
+    let extension = match debug_extension() {
+        Some(extension) => extension,
+        None => return false,
+    };
+
+    let state = Box::new(DebugCallbackState {
+        options,
+        callback: Box::new(callback),
+    });
+
+    unsafe {
+        // ARB_debug_output has no `GL_DEBUG_OUTPUT` switch, as its output is always on in debug
+        // contexts. `GL_DEBUG_OUTPUT_SYNCHRONOUS` has the same value in both extensions.
+        if extension == DebugExtension::Khr {
+            gl::Enable(gl::DEBUG_OUTPUT);
+        }
+
+        gl::Enable(gl::DEBUG_OUTPUT_SYNCHRONOUS);
+        gl::DebugMessageCallback(debug_callback, &*state as *const DebugCallbackState as *const GLvoid);
+    }
+
+    // The previous state (if any) is only dropped once the driver no longer points at it.
+    CALLBACK_STATE.with(|s| *s.borrow_mut() = Some(state));
+
+    true
+}
+
+// Prints every message to stdout, the same way `check_gl_error!` does.
+pub fn install_logger(options: DebugOptions) -> bool {
+    install_callback(options, |message| println!("{}", message))
+}
+
+pub fn remove_callback() {
+    if let Some(extension) = debug_extension() {
+        unsafe {
+            gl::DebugMessageCallback(debug_callback, ptr::null());
+
+            if extension == DebugExtension::Khr {
+                gl::Disable(gl::DEBUG_OUTPUT);
+            }
+        }
+    }
+
+    CALLBACK_STATE.with(|s| *s.borrow_mut() = None);
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum DebugExtension {
+    Khr,
+    Arb,
+}
+
+// `gl` loads `glDebugMessageCallback` from `glDebugMessageCallbackARB` when the core function is
+// missing, and both take the same callback and constants, so the same calls work with either
+// extension. A loaded function doesn't say which one we got, though, so we check the version and
+// the extension strings.
+fn debug_extension() -> Option<DebugExtension> {
+    if !gl::DebugMessageCallback::is_loaded() {
+        return None;
+    }
+
+    let mut major = 0;
+    let mut minor = 0;
+
+    unsafe {
+        gl::GetIntegerv(gl::MAJOR_VERSION, &mut major);
+        gl::GetIntegerv(gl::MINOR_VERSION, &mut minor);
+    }
+
+    if (major, minor) >= (4, 3) || has_extension(b"GL_KHR_debug") {
+        Some(DebugExtension::Khr)
+    } else if has_extension(b"GL_ARB_debug_output") {
+        Some(DebugExtension::Arb)
+    } else {
+        None
+    }
+}
+
+fn has_extension(extension: &[u8]) -> bool {
+    let mut extension_count = 0;
+
+    unsafe {
+        gl::GetIntegerv(gl::NUM_EXTENSIONS, &mut extension_count);
+    }
+
+    (0..extension_count as GLuint).any(|index| unsafe {
+        let name = gl::GetStringi(gl::EXTENSIONS, index);
+
+        !name.is_null() && ::std::ffi::CStr::from_ptr(name as *const GLchar).to_bytes() == extension
+    })
+}
+
+extern "system" fn debug_callback(source: GLenum, ty: GLenum, id: GLuint, severity: GLenum, length: GLsizei,
+                                  message: *const GLchar, user_param: *mut GLvoid)
+{
+    if user_param.is_null() || message.is_null() {
+        return;
+    }
+
+    let state = unsafe { &*(user_param as *const DebugCallbackState) };
+
+    let text = unsafe {
+        // A negative length means the message is NUL-terminated.
+        if length < 0 {
+            ::std::ffi::CStr::from_ptr(message).to_string_lossy().into_owned()
+        } else {
+            String::from_utf8_lossy(slice::from_raw_parts(message as *const u8, length as usize)).into_owned()
+        }
+    };
+
+    let message = DebugMessage {
+        source: DebugSource::from_gl(source),
+        ty: DebugType::from_gl(ty),
+        id,
+        severity: DebugSeverity::from_gl(severity),
+        message: text,
+    };
+
+    if !state.options.accepts(&message) {
+        return;
+    }
+
+    // Unwinding into the driver is undefined behaviour, so we catch any panic here. The panic hook
+    // has already printed the message (and backtrace) by then, so all that's left is to abort.
+    let result = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+        (state.callback)(&message);
+
+        if state.options.abort_on_error && message.ty == DebugType::Error {
+            panic!("OpenGL error: {}", message);
+        }
+    }));
+
+    if result.is_err() {
+        process::abort();
+    }
+}
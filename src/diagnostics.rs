@@ -0,0 +1,338 @@
+use std::fmt;
+
+// Drivers report compile and link errors as free-form text, and each vendor has its own format:
+//
+//   Mesa:   0:12(5): error: `foo' undeclared
+//   NVIDIA: 0(12) : error C1008: undefined variable "foo"
+//   AMD:    ERROR: 0:12: 'foo' : undeclared identifier
+//
+// `InfoLog` parses the common formats into a list of diagnostics, and keeps the raw log around for
+// anything we couldn't make sense of. Lines that don't start a new diagnostic are appended to the
+// previous one, as some drivers split long messages over several lines.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+    Info,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Severity::Error   => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+            Severity::Info    => write!(f, "info"),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub source_index: Option<usize>,
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+    pub message: String,
+}
+
+#[derive(Clone, Debug)]
+pub struct InfoLog {
+    pub raw: String,
+    pub diagnostics: Vec<Diagnostic>,
+
+    // The name and text of each source string, indexed by source string number. These are only
+    // used to print the offending lines.
+    sources: Vec<(String, String)>,
+}
+
+impl InfoLog {
+    pub fn parse(raw: &str) -> InfoLog {
+        let mut diagnostics: Vec<Diagnostic> = Vec::new();
+
+        for line in raw.lines().map(|l| l.trim()).filter(|l| !l.is_empty()) {
+            match parse_line(line) {
+                Some(diagnostic) => diagnostics.push(diagnostic),
+                None => match diagnostics.last_mut() {
+                    Some(previous) => {
+                        previous.message.push('\n');
+                        previous.message.push_str(line);
+                    }
+                    None => diagnostics.push(Diagnostic {
+                        severity: Severity::Info,
+                        source_index: None,
+                        line: None,
+                        column: None,
+                        message: line.to_string(),
+                    }),
+                },
+            }
+        }
+
+        InfoLog {
+            raw: raw.to_string(),
+            diagnostics,
+            sources: Vec::new(),
+        }
+    }
+
+    // Attaches the source strings the log refers to, as `(name, text)` pairs indexed by source
+    // string number.
+    pub fn with_sources(mut self, sources: Vec<(String, String)>) -> InfoLog {
+        self.sources = sources;
+        self
+    }
+
+    pub fn errors(&self) -> Vec<&Diagnostic> {
+        self.diagnostics.iter().filter(|d| d.severity == Severity::Error).collect()
+    }
+}
+
+impl fmt::Display for InfoLog {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for diagnostic in &self.diagnostics {
+            let source = diagnostic.source_index.and_then(|i| self.sources.get(i));
+
+            write!(f, "{}: ", diagnostic.severity)?;
+
+            match (source, diagnostic.source_index, diagnostic.line) {
+                (Some(&(ref name, _)), _, Some(line)) => write!(f, "{}:{}: ", name, line)?,
+                (None, Some(index), Some(line))       => write!(f, "{}:{}: ", index, line)?,
+                _                                     => (),
+            }
+
+            writeln!(f, "{}", diagnostic.message)?;
+
+            let source_line = source
+                .and_then(|&(_, ref text)| diagnostic.line.and_then(|l| text.lines().nth(l.wrapping_sub(1))));
+
+            if let (Some(source_line), Some(line)) = (source_line, diagnostic.line) {
+                let gutter = line.to_string();
+                let padding = " ".repeat(gutter.len());
+
+                writeln!(f, " {} | {}", gutter, source_line)?;
+                writeln!(f, " {} | {}", padding, carets(source_line, diagnostic.column))?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// Points at the reported column if we know it, or underlines the whole line otherwise.
+fn carets(source_line: &str, column: Option<usize>) -> String {
+    let indent = source_line.len() - source_line.trim_start().len();
+
+    match column {
+        Some(column) if column > 0 && column <= source_line.len() => {
+            format!("{}^", " ".repeat(column - 1))
+        }
+        _ => format!("{}{}", " ".repeat(indent), "^".repeat(source_line.trim().len().max(1))),
+    }
+}
+
+fn parse_line(line: &str) -> Option<Diagnostic> {
+    // AMD and Intel put the severity first: `ERROR: 0:12: message`.
+    for &(prefix, severity) in &[("ERROR:", Severity::Error), ("WARNING:", Severity::Warning), ("INFO:", Severity::Info)] {
+        if line.get(..prefix.len()).map_or(false, |p| p.eq_ignore_ascii_case(prefix)) {
+            let rest = line[prefix.len()..].trim_start();
+
+            return Some(match parse_location(rest) {
+                Some((source_index, line, column, message)) => Diagnostic {
+                    severity,
+                    source_index: Some(source_index),
+                    line: Some(line),
+                    column,
+                    message: message.trim().to_string(),
+                },
+                None => Diagnostic {
+                    severity,
+                    source_index: None,
+                    line: None,
+                    column: None,
+                    message: rest.to_string(),
+                },
+            });
+        }
+    }
+
+    // Mesa and NVIDIA put the location first: `0:12(5): error: message` or `0(12) : error C1008:
+    // message`.
+    if let Some((source_index, line, column, rest)) = parse_location(line) {
+        let (severity, message) = parse_severity(rest)?;
+
+        return Some(Diagnostic {
+            severity,
+            source_index: Some(source_index),
+            line: Some(line),
+            column,
+            message: message.to_string(),
+        });
+    }
+
+    // Link errors usually have no location at all: `error: message`.
+    parse_severity(line).map(|(severity, message)| Diagnostic {
+        severity,
+        source_index: None,
+        line: None,
+        column: None,
+        message: message.to_string(),
+    })
+}
+
+// Parses `N:L:`, `N:L(C):` or `N(L) :` at the start of `s`, and returns the source string number,
+// line, column and whatever follows the location.
+fn parse_location(s: &str) -> Option<(usize, usize, Option<usize>, &str)> {
+    let (source_index, s) = parse_number(s)?;
+
+    let (line, column, s) = if s.starts_with(':') {
+        let (line, s) = parse_number(&s[1..])?;
+
+        if s.starts_with('(') {
+            let (column, s) = parse_number(&s[1..])?;
+
+            if !s.starts_with(')') {
+                return None;
+            }
+
+            (line, Some(column), &s[1..])
+        } else {
+            (line, None, s)
+        }
+    } else if s.starts_with('(') {
+        let (line, s) = parse_number(&s[1..])?;
+
+        if !s.starts_with(')') {
+            return None;
+        }
+
+        (line, None, &s[1..])
+    } else {
+        return None;
+    };
+
+    let s = s.trim_start();
+
+    if s.starts_with(':') {
+        Some((source_index, line, column, &s[1..]))
+    } else {
+        None
+    }
+}
+
+fn parse_number(s: &str) -> Option<(usize, &str)> {
+    let len = s.bytes().take_while(|b| b.is_ascii_digit()).count();
+
+    if len == 0 {
+        return None;
+    }
+
+    s[..len].parse().ok().map(|n| (n, &s[len..]))
+}
+
+// Parses `error: message` or `error C1008: message`, where the severity may be followed by a
+// vendor-specific code.
+fn parse_severity(s: &str) -> Option<(Severity, &str)> {
+    let s = s.trim_start();
+    let colon = s.find(':')?;
+
+    let severity = match s[..colon].split_whitespace().next()?.to_lowercase().as_str() {
+        "error" | "fatal"  => Severity::Error,
+        "warning"          => Severity::Warning,
+        "info" | "note"    => Severity::Info,
+        _                  => return None,
+    };
+
+    Some((severity, s[colon + 1..].trim()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &'static str = "void main() {\n    foo = 1;\n}\n";
+
+    fn parse_one(raw: &str) -> Diagnostic {
+        let log = InfoLog::parse(raw);
+        assert_eq!(log.diagnostics.len(), 1);
+
+        log.diagnostics[0].clone()
+    }
+
+    #[test]
+    fn parses_mesa_format() {
+        let diagnostic = parse_one("0:12(5): error: `foo' undeclared\n");
+
+        assert_eq!(diagnostic.severity, Severity::Error);
+        assert_eq!(diagnostic.source_index, Some(0));
+        assert_eq!(diagnostic.line, Some(12));
+        assert_eq!(diagnostic.column, Some(5));
+        assert_eq!(diagnostic.message, "`foo' undeclared");
+    }
+
+    #[test]
+    fn parses_nvidia_format() {
+        let diagnostic = parse_one("0(12) : error C1008: undefined variable \"foo\"\n");
+
+        assert_eq!(diagnostic.severity, Severity::Error);
+        assert_eq!(diagnostic.source_index, Some(0));
+        assert_eq!(diagnostic.line, Some(12));
+        assert_eq!(diagnostic.column, None);
+        assert_eq!(diagnostic.message, "undefined variable \"foo\"");
+    }
+
+    #[test]
+    fn parses_amd_format() {
+        let diagnostic = parse_one("ERROR: 0:12: 'foo' : undeclared identifier\n");
+
+        assert_eq!(diagnostic.severity, Severity::Error);
+        assert_eq!(diagnostic.source_index, Some(0));
+        assert_eq!(diagnostic.line, Some(12));
+        assert_eq!(diagnostic.column, None);
+        assert_eq!(diagnostic.message, "'foo' : undeclared identifier");
+    }
+
+    #[test]
+    fn continuation_lines_are_appended() {
+        let log = InfoLog::parse("0:2(5): error: `foo' undeclared\n  in function main\n0:3(1): warning: unused\n");
+
+        assert_eq!(log.diagnostics.len(), 2);
+        assert_eq!(log.diagnostics[0].message, "`foo' undeclared\nin function main");
+        assert_eq!(log.diagnostics[1].severity, Severity::Warning);
+        assert_eq!(log.errors().len(), 1);
+    }
+
+    #[test]
+    fn continuation_line_with_multibyte_characters() {
+        // Checking for the `ERROR:`-style prefixes slices the line inside the quotation mark, which
+        // used to panic.
+        let log = InfoLog::parse("0:1(1): error: x\nabcd\u{201c}x\n");
+
+        assert_eq!(log.diagnostics.len(), 1);
+        assert_eq!(log.diagnostics[0].message, "x\nabcd\u{201c}x");
+    }
+
+    #[test]
+    fn display_points_at_column() {
+        let log = InfoLog::parse("0:2(5): error: `foo' undeclared\n")
+            .with_sources(vec![("shader.vert".to_string(), SOURCE.to_string())]);
+
+        assert_eq!(log.to_string(), concat!(
+            "error: shader.vert:2: `foo' undeclared\n",
+            " 2 |     foo = 1;\n",
+            "   |     ^\n",
+        ));
+    }
+
+    #[test]
+    fn display_underlines_line_without_column() {
+        let log = InfoLog::parse("ERROR: 0:2: 'foo' : undeclared identifier\n")
+            .with_sources(vec![("shader.vert".to_string(), SOURCE.to_string())]);
+
+        assert_eq!(log.to_string(), concat!(
+            "error: shader.vert:2: 'foo' : undeclared identifier\n",
+            " 2 |     foo = 1;\n",
+            "   |     ^^^^^^^^\n",
+        ));
+    }
+}
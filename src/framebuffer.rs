@@ -0,0 +1,255 @@
+use gl;
+use gl::types::*;
+
+use gl_object::{GlObject, Handle};
+use texture::{CubeFace, Texture2D, Texture2DArray, Texture3D, TextureCube};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Attachment {
+    // The n-th color output of the fragment shader, ie. `layout (location = n) out vec4 ...`.
+    Color(u32),
+    Depth,
+    Stencil,
+    DepthStencil,
+}
+
+impl From<Attachment> for GLenum {
+    fn from(attachment: Attachment) -> Self {
+        match attachment {
+            Attachment::Color(n)     => gl::COLOR_ATTACHMENT0 + n,
+            Attachment::Depth        => gl::DEPTH_ATTACHMENT,
+            Attachment::Stencil      => gl::STENCIL_ATTACHMENT,
+            Attachment::DepthStencil => gl::DEPTH_STENCIL_ATTACHMENT,
+        }
+    }
+}
+
+// Why a framebuffer can't be rendered to, as reported by `glCheckFramebufferStatus`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FramebufferError {
+    // An attachment is missing its storage, or has a format that can't be rendered to.
+    IncompleteAttachment,
+    // Nothing is attached.
+    MissingAttachment,
+    IncompleteDrawBuffer,
+    IncompleteReadBuffer,
+    // The driver doesn't support this combination of formats.
+    Unsupported,
+    // The attachments have different numbers of samples.
+    IncompleteMultisample,
+    // Some attachments are layered and some aren't.
+    IncompleteLayerTargets,
+    Unknown(GLenum),
+}
+
+impl FramebufferError {
+    fn from_status(status: GLenum) -> Option<FramebufferError> {
+        match status {
+            gl::FRAMEBUFFER_COMPLETE                      => None,
+            gl::FRAMEBUFFER_INCOMPLETE_ATTACHMENT         => Some(FramebufferError::IncompleteAttachment),
+            gl::FRAMEBUFFER_INCOMPLETE_MISSING_ATTACHMENT => Some(FramebufferError::MissingAttachment),
+            gl::FRAMEBUFFER_INCOMPLETE_DRAW_BUFFER        => Some(FramebufferError::IncompleteDrawBuffer),
+            gl::FRAMEBUFFER_INCOMPLETE_READ_BUFFER        => Some(FramebufferError::IncompleteReadBuffer),
+            gl::FRAMEBUFFER_UNSUPPORTED                   => Some(FramebufferError::Unsupported),
+            gl::FRAMEBUFFER_INCOMPLETE_MULTISAMPLE        => Some(FramebufferError::IncompleteMultisample),
+            gl::FRAMEBUFFER_INCOMPLETE_LAYER_TARGETS      => Some(FramebufferError::IncompleteLayerTargets),
+            other                                         => Some(FramebufferError::Unknown(other)),
+        }
+    }
+}
+
+// Storage for an attachment that is rendered to but never sampled, typically the depth and
+// stencil buffers when rendering to a texture.
+pub struct Renderbuffer {
+    id: Handle,
+    width: u32,
+    height: u32,
+}
+
+impl Drop for Renderbuffer {
+    fn drop(&mut self) {
+        unsafe { gl::DeleteRenderbuffers(1, &self.id); }
+    }
+}
+
+impl GlObject for Renderbuffer {
+    #[inline]
+    fn id(&self) -> Handle {
+        self.id
+    }
+}
+
+impl Renderbuffer {
+    // `internal_format` must be renderable, eg. `gl::RGBA8` or `gl::DEPTH24_STENCIL8`.
+    pub fn new(internal_format: GLenum, width: u32, height: u32) -> Renderbuffer {
+        Renderbuffer::with_samples(internal_format, width, height, 0)
+    }
+
+    // A multisampled renderbuffer. All the attachments of a framebuffer must use the same number
+    // of samples.
+    pub fn with_samples(internal_format: GLenum, width: u32, height: u32, samples: u32) -> Renderbuffer {
+        let mut id = 0;
+        unsafe {
+            gl::GenRenderbuffers(1, &mut id);
+            gl::BindRenderbuffer(gl::RENDERBUFFER, id);
+            gl::RenderbufferStorageMultisample(gl::RENDERBUFFER, samples as GLsizei, internal_format,
+                width as GLsizei, height as GLsizei);
+            gl::BindRenderbuffer(gl::RENDERBUFFER, 0);
+        }
+
+        Renderbuffer {
+            id,
+            width,
+            height,
+        }
+    }
+
+    #[inline]
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    #[inline]
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+}
+
+// A render target other than the window. Attach textures or renderbuffers, check that the result
+// is complete, then bind it to draw into the attachments instead of the window.
+//
+//     let color = Texture2D::builder().mipmaps(false).with_size(1024, 768);
+//     let depth = Renderbuffer::new(gl::DEPTH24_STENCIL8, 1024, 768);
+//
+//     let mut framebuffer = Framebuffer::new();
+//     framebuffer.attach_texture(Attachment::Color(0), &color);
+//     framebuffer.attach_renderbuffer(Attachment::DepthStencil, &depth);
+//     framebuffer.check()?;
+//
+// The framebuffer doesn't own its attachments, which must outlive its use.
+pub struct Framebuffer {
+    id: Handle,
+    color_attachments: Vec<GLenum>,
+}
+
+impl Drop for Framebuffer {
+    fn drop(&mut self) {
+        unsafe { gl::DeleteFramebuffers(1, &self.id); }
+    }
+}
+
+impl GlObject for Framebuffer {
+    #[inline]
+    fn id(&self) -> Handle {
+        self.id
+    }
+}
+
+impl Framebuffer {
+    pub fn new() -> Framebuffer {
+        let mut id = 0;
+        unsafe { gl::GenFramebuffers(1, &mut id); }
+
+        Framebuffer {
+            id,
+            color_attachments: Vec::new(),
+        }
+    }
+
+    pub fn attach_texture(&mut self, attachment: Attachment, texture: &Texture2D) {
+        self.attach(attachment, |target, attachment| unsafe {
+            gl::FramebufferTexture2D(target, attachment, gl::TEXTURE_2D, texture.id(), 0);
+        });
+    }
+
+    pub fn attach_cube_face(&mut self, attachment: Attachment, texture: &TextureCube, face: CubeFace) {
+        self.attach(attachment, |target, attachment| unsafe {
+            gl::FramebufferTexture2D(target, attachment, face.into(), texture.id(), 0);
+        });
+    }
+
+    pub fn attach_array_layer(&mut self, attachment: Attachment, texture: &Texture2DArray, layer: u32) {
+        self.attach(attachment, |target, attachment| unsafe {
+            gl::FramebufferTextureLayer(target, attachment, texture.id(), 0, layer as GLint);
+        });
+    }
+
+    pub fn attach_3d_slice(&mut self, attachment: Attachment, texture: &Texture3D, slice: u32) {
+        self.attach(attachment, |target, attachment| unsafe {
+            gl::FramebufferTextureLayer(target, attachment, texture.id(), 0, slice as GLint);
+        });
+    }
+
+    pub fn attach_renderbuffer(&mut self, attachment: Attachment, renderbuffer: &Renderbuffer) {
+        self.attach(attachment, |target, attachment| unsafe {
+            gl::FramebufferRenderbuffer(target, attachment, gl::RENDERBUFFER, renderbuffer.id());
+        });
+    }
+
+    fn attach<F: FnOnce(GLenum, GLenum)>(&mut self, attachment: Attachment, attach: F) {
+        let is_color = match attachment {
+            Attachment::Color(_) => true,
+            _ => false,
+        };
+
+        let attachment = GLenum::from(attachment);
+
+        if is_color && !self.color_attachments.contains(&attachment) {
+            self.color_attachments.push(attachment);
+            self.color_attachments.sort();
+        }
+
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, self.id);
+
+            attach(gl::FRAMEBUFFER, attachment);
+
+            // Fragment shader outputs are written to the attachments listed as draw buffers. We
+            // list every color attachment, in order, so that output n goes to `Color(n)` as long
+            // as they are attached without gaps. With no color attachments (eg. for a shadow map),
+            // there's nothing to draw or read colors to.
+            if self.color_attachments.is_empty() {
+                gl::DrawBuffer(gl::NONE);
+                gl::ReadBuffer(gl::NONE);
+            } else {
+                gl::DrawBuffers(self.color_attachments.len() as GLsizei, self.color_attachments.as_ptr());
+                gl::ReadBuffer(self.color_attachments[0]);
+            }
+
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+        }
+    }
+
+    // Checks that the framebuffer can be rendered to.
+    pub fn check(&self) -> Result<(), FramebufferError> {
+        let status = unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, self.id);
+            let status = gl::CheckFramebufferStatus(gl::FRAMEBUFFER);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+
+            status
+        };
+
+        match FramebufferError::from_status(status) {
+            Some(error) => Err(error),
+            None => Ok(()),
+        }
+    }
+
+    // Makes the framebuffer the target of draw calls (and the source of reads). Remember to set
+    // the viewport to the size of the attachments.
+    pub fn bind(&self) {
+        unsafe { gl::BindFramebuffer(gl::FRAMEBUFFER, self.id); }
+    }
+
+    // Goes back to rendering to the window.
+    pub fn unbind(&self) {
+        unsafe { gl::BindFramebuffer(gl::FRAMEBUFFER, 0); }
+    }
+}
+
+impl Default for Framebuffer {
+    fn default() -> Framebuffer {
+        Framebuffer::new()
+    }
+}
@@ -1,21 +1,44 @@
 extern crate glutin;
 extern crate gl;
+extern crate image;
+extern crate png;
 
+pub mod app;
+pub mod binary_cache;
+pub mod buffer;
 pub mod debug;
+pub mod diagnostics;
+pub mod framebuffer;
+pub mod preprocessor;
 pub mod program;
+pub mod reflection;
+pub mod render_state;
+pub mod screenshot;
+pub mod skybox;
+pub mod texture;
+pub mod time;
+pub mod uniform;
+pub mod vertex_array;
 
 mod gl_object;
 
-pub use glutin::Event;
+pub use glutin::{ElementState, Event, HeadlessContext, VirtualKeyCode, Window};
 
-pub fn create_window(title: &str) -> glutin::Window {
+// With `debug`, asks for a debug context. Drivers report far more through
+// `debug::install_callback` in debug contexts, at the cost of some performance.
+pub fn create_window(title: &str, debug: bool) -> glutin::Window {
+    build_window(title, 1024, 768, debug)
+}
+
+fn build_window(title: &str, width: u32, height: u32, debug: bool) -> glutin::Window {
     use glutin::{Api, GlProfile, GlRequest, WindowBuilder};
 
     let window = WindowBuilder::new()
-        .with_dimensions(1024, 768)
+        .with_dimensions(width, height)
         .with_title(title)
         .with_gl(GlRequest::Specific(Api::OpenGl, (3, 3)))
         .with_gl_profile(GlProfile::Core)
+        .with_gl_debug_flag(debug)
         .with_vsync()
         .build()
         .unwrap();
@@ -24,5 +47,33 @@ pub fn create_window(title: &str) -> glutin::Window {
 
     gl::load_with(|symbol| window.get_proc_address(symbol) as *const _);
 
+    // Whatever state was applied before belongs to another context.
+    render_state::invalidate();
+
     window
 }
+
+// A context without a window, for rendering where there's no display, eg. on CI machines. On
+// Linux this uses OSMesa, which works with Mesa's llvmpipe software rasterizer, so no GPU is
+// needed either.
+//
+// The context's default framebuffer is `width` by `height` pixels, but it isn't shown anywhere:
+// read the results back with `glReadPixels`, or render to a `framebuffer::Framebuffer`. Unlike
+// `create_window`, this returns an error, so callers can skip offscreen work when no headless
+// implementation is available.
+pub fn create_headless_context(width: u32, height: u32) -> Result<glutin::HeadlessContext, glutin::CreationError> {
+    use glutin::{Api, GlProfile, GlRequest, HeadlessRendererBuilder};
+
+    let context = HeadlessRendererBuilder::new(width, height)
+        .with_gl(GlRequest::Specific(Api::OpenGl, (3, 3)))
+        .with_gl_profile(GlProfile::Core)
+        .build()?;
+
+    // A fresh context can always be made current on the thread that created it.
+    unsafe { context.make_current().unwrap() };
+
+    gl::load_with(|symbol| context.get_proc_address(symbol) as *const _);
+    render_state::invalidate();
+
+    Ok(context)
+}
@@ -0,0 +1,320 @@
+use std::fs;
+use std::io;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+// GLSL has no `#include` directive of its own, so we resolve them ourselves before handing the
+// source over to the driver.
+//
+// Every file that ends up in the output gets a source string number, and we insert `#line`
+// directives around each included file. That way, the line numbers in the driver's info log still
+// point at the original files, and `PreprocessedSource::named_sources` lets `InfoLog` print the file
+// paths and lines that the source string numbers refer to.
+
+#[derive(Debug)]
+pub enum PreprocessorError {
+    IoError(PathBuf, io::Error),
+    MalformedInclude(PathBuf, usize),
+    IncludeNotFound(PathBuf, usize, String),
+    IncludeCycle(Vec<PathBuf>),
+}
+
+pub struct PreprocessedSource {
+    pub source: String,
+    pub files: Vec<PathBuf>,
+
+    // The original contents of each file, indexed by source string number.
+    pub file_sources: Vec<String>,
+}
+
+impl PreprocessedSource {
+    pub fn file(&self, source_index: usize) -> Option<&Path> {
+        self.files.get(source_index).map(|p| p.as_path())
+    }
+
+    // The files and their contents as `(name, text)` pairs, as expected by `InfoLog::with_sources`.
+    pub fn named_sources(&self) -> Vec<(String, String)> {
+        self.files.iter()
+            .map(|p| p.display().to_string())
+            .zip(self.file_sources.iter().cloned())
+            .collect()
+    }
+}
+
+pub struct Preprocessor {
+    include_dirs: Vec<PathBuf>,
+}
+
+impl Preprocessor {
+    pub fn new() -> Preprocessor {
+        Preprocessor {
+            include_dirs: Vec::new(),
+        }
+    }
+
+    // Included files are first looked up relative to the file that includes them, and then in
+    // each include directory, in the order they were added.
+    pub fn with_include_dir<P: AsRef<Path>>(mut self, dir: P) -> Preprocessor {
+        self.include_dirs.push(dir.as_ref().to_path_buf());
+        self
+    }
+
+    pub fn process_file<P: AsRef<Path>>(&self, path: P) -> Result<PreprocessedSource, PreprocessorError> {
+        let path = path.as_ref();
+        let source = read_source(path).map_err(|e| PreprocessorError::IoError(path.to_path_buf(), e))?;
+
+        self.process_source(path, &source)
+    }
+
+    // Same as `process_file`, for a source that isn't read from a file. `path` is the name the
+    // source goes by in diagnostics, and the files it includes are looked up relative to it, so
+    // a bare name like `"vertex"` resolves them relative to the working directory.
+    pub fn process_source<P: AsRef<Path>>(&self, path: P, source: &str) -> Result<PreprocessedSource, PreprocessorError> {
+        let mut output = PreprocessedSource {
+            source: String::new(),
+            files: Vec::new(),
+            file_sources: Vec::new(),
+        };
+
+        self.include(path.as_ref(), source.to_string(), &mut Vec::new(), &mut output)?;
+
+        Ok(output)
+    }
+
+    fn include(&self, path: &Path, source: String, stack: &mut Vec<PathBuf>, output: &mut PreprocessedSource)
+        -> Result<(), PreprocessorError>
+    {
+        let canonical_path = canonicalize(path);
+
+        if stack.contains(&canonical_path) {
+            let mut cycle = stack.clone();
+            cycle.push(canonical_path);
+
+            return Err(PreprocessorError::IncludeCycle(cycle));
+        }
+
+        // A file that is included more than once keeps the same source string number, even if it is
+        // reached through different relative paths.
+        let source_index = match output.files.iter().position(|p| canonicalize(p) == canonical_path) {
+            Some(index) => index,
+            None => {
+                output.files.push(path.to_path_buf());
+                output.file_sources.push(source.clone());
+                output.files.len() - 1
+            }
+        };
+
+        let is_root = stack.is_empty();
+        stack.push(canonical_path);
+
+        if !is_root {
+            output.source.push_str(&format!("#line 1 {}\n", source_index));
+        }
+
+        for (i, line) in source.lines().enumerate() {
+            let line_number = i + 1;
+
+            match parse_include(line) {
+                Some(Ok(name)) => {
+                    let include_path = self.resolve(path, name).ok_or_else(|| {
+                        PreprocessorError::IncludeNotFound(path.to_path_buf(), line_number, name.to_string())
+                    })?;
+
+                    let include_source = read_source(&include_path)
+                        .map_err(|e| PreprocessorError::IoError(include_path.clone(), e))?;
+
+                    self.include(&include_path, include_source, stack, output)?;
+
+                    // Back in the including file, continue from the line after the `#include`.
+                    output.source.push_str(&format!("#line {} {}\n", line_number + 1, source_index));
+                }
+                Some(Err(())) => {
+                    return Err(PreprocessorError::MalformedInclude(path.to_path_buf(), line_number));
+                }
+                None => {
+                    // Only the root file may declare the GLSL version, as it must be the very first
+                    // directive in the shader. We keep an empty line so the line numbers still match.
+                    if is_root || !line.trim_start().starts_with("#version") {
+                        output.source.push_str(line);
+                    }
+
+                    output.source.push('\n');
+                }
+            }
+        }
+
+        stack.pop();
+
+        Ok(())
+    }
+
+    fn resolve(&self, including_file: &Path, name: &str) -> Option<PathBuf> {
+        let relative_dir = including_file.parent().map(|p| p.to_path_buf()).unwrap_or_default();
+
+        Some(relative_dir).into_iter()
+            .chain(self.include_dirs.iter().cloned())
+            .map(|dir| dir.join(name))
+            .find(|path| path.is_file())
+    }
+}
+
+impl Default for Preprocessor {
+    fn default() -> Preprocessor {
+        Preprocessor::new()
+    }
+}
+
+// Returns `None` if the line is not an include directive, or the included file name if it is a
+// well-formed `#include "file"` or `#include <file>`, optionally followed by a comment.
+fn parse_include(line: &str) -> Option<Result<&str, ()>> {
+    let line = line.trim();
+
+    if !line.starts_with('#') {
+        return None;
+    }
+
+    let directive = line[1..].trim_start();
+
+    if !directive.starts_with("include") {
+        return None;
+    }
+
+    let argument = directive["include".len()..].trim();
+
+    let closing = match argument.chars().next() {
+        Some('"') => '"',
+        Some('<') => '>',
+        _ => return Some(Err(())),
+    };
+
+    let name_len = match argument[1..].find(closing) {
+        Some(len) if len > 0 => len,
+        _ => return Some(Err(())),
+    };
+
+    let rest = argument[name_len + 2..].trim_start();
+    let is_comment = rest.starts_with("//") || (rest.starts_with("/*") && rest.ends_with("*/") && rest.len() >= 4);
+
+    if !rest.is_empty() && !is_comment {
+        return Some(Err(()));
+    }
+
+    Some(Ok(&argument[1..name_len + 1]))
+}
+
+fn canonicalize(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+fn read_source(path: &Path) -> io::Result<String> {
+    let mut source = String::new();
+    fs::File::open(path)?.read_to_string(&mut source)?;
+
+    Ok(source)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::env;
+
+    // Creates an empty directory for a test, and writes `files` into it as `(name, contents)`.
+    fn write_files(test: &str, files: &[(&str, &str)]) -> PathBuf {
+        let dir = env::temp_dir().join(format!("learn_opengl_preprocessor_{}", test));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        for &(name, contents) in files {
+            fs::write(dir.join(name), contents).unwrap();
+        }
+
+        dir
+    }
+
+    #[test]
+    fn nested_includes_restore_line_numbers() {
+        let dir = write_files("nested", &[
+            ("shader.vert", "#version 330 core\n#include \"a.glsl\"\nvoid main() {}\n"),
+            ("a.glsl", "float a;\n#include \"b.glsl\"\nfloat c;\n"),
+            ("b.glsl", "float b;\n"),
+        ]);
+
+        let output = Preprocessor::new().process_file(dir.join("shader.vert")).unwrap();
+
+        assert_eq!(output.source, "#version 330 core\n\
+                                   #line 1 1\nfloat a;\n\
+                                   #line 1 2\nfloat b;\n\
+                                   #line 3 1\nfloat c;\n\
+                                   #line 3 0\nvoid main() {}\n");
+        assert_eq!(output.files, [dir.join("shader.vert"), dir.join("a.glsl"), dir.join("b.glsl")]);
+    }
+
+    #[test]
+    fn version_is_stripped_from_included_files() {
+        let dir = write_files("version", &[
+            ("shader.vert", "#version 330 core\n#include \"common.glsl\"\n"),
+            ("common.glsl", "  #version 330 core\nfloat a;\n"),
+        ]);
+
+        let output = Preprocessor::new().process_file(dir.join("shader.vert")).unwrap();
+
+        assert_eq!(output.source, "#version 330 core\n#line 1 1\n\nfloat a;\n#line 3 0\n");
+    }
+
+    #[test]
+    fn include_cycle_is_an_error() {
+        let dir = write_files("cycle", &[
+            ("shader.vert", "#include \"a.glsl\"\n"),
+            ("a.glsl", "#include \"b.glsl\"\n"),
+            ("b.glsl", "#include \"a.glsl\"\n"),
+        ]);
+
+        match Preprocessor::new().process_file(dir.join("shader.vert")) {
+            Err(PreprocessorError::IncludeCycle(cycle)) => {
+                let names: Vec<_> = cycle.iter().map(|p| p.file_name().unwrap().to_str().unwrap()).collect();
+                assert_eq!(names, ["shader.vert", "a.glsl", "b.glsl", "a.glsl"]);
+            }
+            _ => panic!("expected an include cycle"),
+        }
+    }
+
+    #[test]
+    fn same_file_through_different_paths_keeps_its_number() {
+        let dir = write_files("same_file", &[
+            ("shader.vert", "#include \"common.glsl\"\n#include \"./common.glsl\"\n"),
+            ("common.glsl", "float a;\n"),
+        ]);
+
+        let output = Preprocessor::new().process_file(dir.join("shader.vert")).unwrap();
+
+        assert_eq!(output.source, "#line 1 1\nfloat a;\n#line 2 0\n#line 1 1\nfloat a;\n#line 3 0\n");
+        assert_eq!(output.files.len(), 2);
+    }
+
+    #[test]
+    fn include_can_be_followed_by_a_comment() {
+        assert_eq!(parse_include("#include \"a.glsl\" // shared code"), Some(Ok("a.glsl")));
+        assert_eq!(parse_include("#include <a.glsl> /* shared code */"), Some(Ok("a.glsl")));
+        assert_eq!(parse_include("  # include \"a.glsl\""), Some(Ok("a.glsl")));
+        assert_eq!(parse_include("float a;"), None);
+    }
+
+    #[test]
+    fn malformed_include_is_an_error() {
+        assert_eq!(parse_include("#include a.glsl"), Some(Err(())));
+        assert_eq!(parse_include("#include \"a.glsl"), Some(Err(())));
+        assert_eq!(parse_include("#include \"\""), Some(Err(())));
+        assert_eq!(parse_include("#include \"a.glsl\" b.glsl"), Some(Err(())));
+
+        let dir = write_files("malformed", &[("shader.vert", "#version 330 core\n#include a.glsl\n")]);
+
+        match Preprocessor::new().process_file(dir.join("shader.vert")) {
+            Err(PreprocessorError::MalformedInclude(path, line)) => {
+                assert_eq!(path, dir.join("shader.vert"));
+                assert_eq!(line, 2);
+            }
+            _ => panic!("expected a malformed include"),
+        }
+    }
+}
@@ -1,19 +1,38 @@
 use gl;
 use gl::types::*;
 
+use std::cell::RefCell;
+use std::collections::HashMap;
 use std::ffi;
+use std::fs;
 use std::ptr;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
 
+use binary_cache::ProgramCache;
+use debug::GlError;
+use diagnostics::InfoLog;
 use gl_object::{GlObject, Handle};
+use preprocessor::{Preprocessor, PreprocessorError};
+use uniform::Uniform;
 
 #[derive(Debug)]
 pub enum ProgramCreationError {
-    LinkError(String),
+    LinkError(InfoLog),
     InvalidInfoLog,
 }
 
+#[derive(Debug)]
+pub enum UniformError {
+    InvalidName(String),
+    NotFound(String),
+}
+
 pub struct Program {
     id: Handle,
+
+    // Uniform locations don't change after linking, so we only need to look each one up once.
+    uniform_locations: RefCell<HashMap<String, GLint>>,
 }
 
 impl Drop for Program {
@@ -39,7 +58,8 @@ impl Program {
         unsafe {
             // 1. Create a program object.
             program = Program {
-                id: gl::CreateProgram()
+                id: gl::CreateProgram(),
+                uniform_locations: RefCell::new(HashMap::new()),
             };
 
             // 2. Attach the shaders. Notice we don't need to specify their type, as OpenGL already has
@@ -48,7 +68,12 @@ impl Program {
                 gl::AttachShader(program.id(), shader.id());
             }
 
-            // 3. Link the program.
+            // 3. Link the program. Some drivers only keep what `Program::binary` needs when asked to
+            // before linking.
+            if gl::ProgramParameteri::is_loaded() {
+                gl::ProgramParameteri(program.id(), gl::PROGRAM_BINARY_RETRIEVABLE_HINT, gl::TRUE as GLint);
+            }
+
             gl::LinkProgram(program.id());
 
             // 4. Retrieve the link status.
@@ -73,11 +98,77 @@ impl Program {
                     .to_string_lossy()
                     .to_string();
 
-                Err(ProgramCreationError::LinkError(log))
+                Err(ProgramCreationError::LinkError(InfoLog::parse(&log)))
             }
         }
     }
 
+    // Creates a program from a binary previously returned by `Program::binary`. Drivers are free to
+    // reject binaries, eg. after a driver update, so this returns `None` when loading fails and
+    // the program must be compiled from source instead.
+    pub fn from_binary(format: GLenum, binary: &[u8]) -> Option<Program> {
+        if !gl::ProgramBinary::is_loaded() {
+            return None;
+        }
+
+        let mut link_status = gl::FALSE as GLint;
+
+        let program = Program {
+            id: unsafe { gl::CreateProgram() },
+            uniform_locations: RefCell::new(HashMap::new()),
+        };
+
+        // Errors left over from earlier calls would be taken for ours below, so we report them
+        // first, the same way `check_gl_error!` does.
+        for error in GlError::drain() {
+            println!("{}:{}: before loading a program binary: {}", file!(), line!(), error);
+        }
+
+        unsafe {
+            gl::ProgramBinary(program.id, format, binary.as_ptr() as *const GLvoid, binary.len() as GLsizei);
+        }
+
+        // A rejected binary raises an error, eg. `INVALID_ENUM` for a format the driver no longer
+        // supports. We handle the failure, so it mustn't show up in the next error check.
+        let errors = GlError::drain();
+
+        unsafe {
+            gl::GetProgramiv(program.id, gl::LINK_STATUS, &mut link_status);
+        }
+
+        if errors.is_empty() && link_status == (gl::TRUE as GLint) {
+            Some(program)
+        } else {
+            None
+        }
+    }
+
+    // Returns the binary format and the linked program binary, or `None` if the driver doesn't
+    // support retrieving program binaries (they're only core since OpenGL 4.1).
+    pub fn binary(&self) -> Option<(GLenum, Vec<u8>)> {
+        if !gl::GetProgramBinary::is_loaded() {
+            return None;
+        }
+
+        let mut len = 0;
+        unsafe { gl::GetProgramiv(self.id, gl::PROGRAM_BINARY_LENGTH, &mut len); }
+
+        if len <= 0 {
+            return None;
+        }
+
+        let mut format = 0;
+        let mut buf = vec![0u8; len as usize];
+
+        unsafe {
+            gl::GetProgramBinary(self.id, len, &mut len, &mut format, buf.as_mut_ptr() as *mut GLvoid);
+        }
+
+        buf.truncate(len as usize);
+
+        Some((format, buf))
+    }
+
     pub fn activate(&self) {
         unsafe { gl::UseProgram(self.id); }
     }
@@ -85,6 +176,41 @@ impl Program {
     pub fn deactivate(&self) {
         unsafe { gl::UseProgram(0); }
     }
+
+    // Uniforms are set on the active program, so this activates the program first.
+    pub fn set_uniform<U: Uniform>(&self, name: &str, value: U) -> Result<(), UniformError> {
+        let location = self.uniform_location(name)?;
+
+        self.activate();
+        unsafe { value.set(location); }
+
+        Ok(())
+    }
+
+    pub fn uniform_location(&self, name: &str) -> Result<GLint, UniformError> {
+        let cached = self.uniform_locations.borrow().get(name).cloned();
+
+        let location = match cached {
+            Some(location) => location,
+            None => {
+                let c_name = ffi::CString::new(name)
+                    .map_err(|_| UniformError::InvalidName(name.to_string()))?;
+
+                let location = unsafe { gl::GetUniformLocation(self.id, c_name.as_ptr()) };
+                self.uniform_locations.borrow_mut().insert(name.to_string(), location);
+
+                location
+            }
+        };
+
+        // OpenGL returns -1 for names that don't match an active uniform. This also happens when
+        // the uniform is declared but optimized away by the compiler because it's never used.
+        if location == -1 {
+            Err(UniformError::NotFound(name.to_string()))
+        } else {
+            Ok(location)
+        }
+    }
 }
 
 #[derive(Clone, Copy)]
@@ -123,13 +249,35 @@ impl ShaderType {
             _ => None
         }
     }
+
+    fn name(&self) -> &'static str {
+        match *self {
+            ShaderType::Vertex         => "vertex",
+            ShaderType::Fragment       => "fragment",
+            ShaderType::Geometry       => "geometry",
+            ShaderType::TessControl    => "tess_control",
+            ShaderType::TessEvaluation => "tess_evaluation",
+            ShaderType::Compute        => "compute",
+        }
+    }
+
+    // Shader files can have compound extensions (eg. `.fs.glsl`), so `Path::extension` is not
+    // enough. Instead, we try every suffix of the file name that starts with a dot, from the
+    // longest to the shortest.
+    fn from_path(path: &Path) -> Option<ShaderType> {
+        let file_name = path.file_name()?.to_str()?;
+
+        file_name.match_indices('.')
+            .filter_map(|(i, _)| ShaderType::from_extension(&file_name[i..]))
+            .next()
+    }
 }
 
 
 #[derive(Debug)]
 pub enum ShaderCreationError {
     InvalidSource,
-    CompileError(String),
+    CompileError(InfoLog),
     InvalidInfoLog,
 }
 
@@ -181,7 +329,9 @@ impl Shader {
                 .to_string();
 
 
-            Err(ShaderCreationError::CompileError(log))
+            let sources = vec![(ty.name().to_string(), source.to_string())];
+
+            Err(ShaderCreationError::CompileError(InfoLog::parse(&log).with_sources(sources)))
         }
     }
 }
@@ -193,6 +343,7 @@ impl Shader {
 
 #[derive(Debug)]
 pub enum SourceCompilerError {
+    PreprocessorError(PreprocessorError),
     ShaderCreationError(ShaderCreationError),
     ProgramCreationError(ProgramCreationError),
 }
@@ -200,20 +351,225 @@ pub enum SourceCompilerError {
 pub struct SourceCompiler {}
 
 impl SourceCompiler {
+    // The sources go through the `Preprocessor` like files do, so they can `#include` files. Those
+    // are looked up relative to the working directory.
     pub fn compile(shader_sources: &[(ShaderType, &str)]) -> Result<Program, SourceCompilerError> {
-        let mut shaders = Vec::new();
+        SourceCompiler::compile_preprocessed(shader_sources, None)
+    }
+
+    // Same as `compile`, but reuses a previously linked program binary from the cache when there
+    // is one for these sources. As with `FileCompiler`, the cache key covers the included files.
+    pub fn compile_cached(shader_sources: &[(ShaderType, &str)], cache: &ProgramCache)
+        -> Result<Program, SourceCompilerError>
+    {
+        SourceCompiler::compile_preprocessed(shader_sources, Some(cache))
+    }
+
+    fn compile_preprocessed(shader_sources: &[(ShaderType, &str)], cache: Option<&ProgramCache>)
+        -> Result<Program, SourceCompilerError>
+    {
+        let preprocessor = Preprocessor::new();
+        let mut preprocessed_sources = Vec::new();
 
         for &(ty, source) in shader_sources {
-            let shader = Shader::new(ty, source)
-                .map_err(|e| SourceCompilerError::ShaderCreationError(e))?;
+            // Diagnostics refer to the source by its shader type, as `Shader::new` does.
+            let preprocessed = preprocessor.process_source(ty.name(), source)
+                .map_err(|e| SourceCompilerError::PreprocessorError(e))?;
+
+            preprocessed_sources.push((ty, preprocessed));
+        }
+
+        let compile = || {
+            let mut shaders = Vec::new();
+
+            for &(ty, ref preprocessed) in &preprocessed_sources {
+                let shader = Shader::new(ty, &preprocessed.source)
+                    .map_err(|e| match e {
+                        ShaderCreationError::CompileError(log) =>
+                            ShaderCreationError::CompileError(log.with_sources(preprocessed.named_sources())),
+                        e => e,
+                    })
+                    .map_err(|e| SourceCompilerError::ShaderCreationError(e))?;
+
+                shaders.push(shader);
+            }
+
+            Program::link(&shaders).map_err(|e| SourceCompilerError::ProgramCreationError(e))
+        };
+
+        match cache {
+            Some(cache) => {
+                let sources: Vec<_> = preprocessed_sources.iter()
+                    .map(|&(ty, ref preprocessed)| (ty, preprocessed.source.as_str()))
+                    .collect();
+
+                cache.get_or_compile(&sources, compile)
+            }
+            None => compile(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum FileCompilerError {
+    UnknownShaderType(PathBuf),
+    PreprocessorError(PreprocessorError),
+    ShaderCreationError(PathBuf, ShaderCreationError),
+    ProgramCreationError(ProgramCreationError),
+}
+
+pub struct FileCompiler {}
+
+impl FileCompiler {
+    pub fn compile<P: AsRef<Path>>(paths: &[P]) -> Result<Program, FileCompilerError> {
+        FileCompiler::compile_with_dependencies(paths, None, &mut Vec::new())
+    }
+
+    // Same as `compile`, but reuses a previously linked program binary from the cache when there
+    // is one for these files. The cache key covers the preprocessed sources, so changing an
+    // included file also invalidates the cached binary.
+    pub fn compile_cached<P: AsRef<Path>>(paths: &[P], cache: &ProgramCache) -> Result<Program, FileCompilerError> {
+        FileCompiler::compile_with_dependencies(paths, Some(cache), &mut Vec::new())
+    }
+
+    // Compiles the program, and adds every file that went into it to `dependencies`, including the
+    // ones pulled in by `#include` directives. The files are added even if compiling or linking
+    // fails, so that fixing any of them can trigger a new attempt.
+    fn compile_with_dependencies<P: AsRef<Path>>(paths: &[P], cache: Option<&ProgramCache>,
+                                                 dependencies: &mut Vec<PathBuf>)
+        -> Result<Program, FileCompilerError>
+    {
+        let preprocessor = Preprocessor::new();
+        let mut preprocessed_files = Vec::new();
+
+        for path in paths {
+            let path = path.as_ref();
+
+            let ty = ShaderType::from_path(path)
+                .ok_or_else(|| FileCompilerError::UnknownShaderType(path.to_path_buf()))?;
+
+            let preprocessed = preprocessor.process_file(path)
+                .map_err(|e| FileCompilerError::PreprocessorError(e))?;
+
+            dependencies.extend(preprocessed.files.iter().cloned());
+            preprocessed_files.push((path, ty, preprocessed));
+        }
 
-            shaders.push(shader);
+        let compile = || {
+            let mut shaders = Vec::new();
+
+            for &(path, ty, ref preprocessed) in &preprocessed_files {
+                let shader = Shader::new(ty, &preprocessed.source)
+                    .map_err(|e| match e {
+                        // The info log refers to files by their source string number, so we attach
+                        // the files themselves to be able to show the offending lines.
+                        ShaderCreationError::CompileError(log) =>
+                            ShaderCreationError::CompileError(log.with_sources(preprocessed.named_sources())),
+                        e => e,
+                    })
+                    .map_err(|e| FileCompilerError::ShaderCreationError(path.to_path_buf(), e))?;
+
+                shaders.push(shader);
+            }
+
+            Program::link(&shaders).map_err(|e| FileCompilerError::ProgramCreationError(e))
+        };
+
+        match cache {
+            Some(cache) => {
+                let sources: Vec<_> = preprocessed_files.iter()
+                    .map(|&(_, ty, ref preprocessed)| (ty, preprocessed.source.as_str()))
+                    .collect();
+
+                cache.get_or_compile(&sources, compile)
+            }
+            None => compile(),
+        }
+    }
+}
+
+// The `WatchingCompiler` keeps track of the files a program was compiled from, and recompiles the
+// program whenever one of them changes. We simply poll the files' modification times, which is
+// cheap enough to do once per frame.
+pub struct WatchingCompiler {
+    paths: Vec<PathBuf>,
+    watched: Vec<(PathBuf, Option<SystemTime>)>,
+    program: Program,
+    cache: Option<ProgramCache>,
+}
+
+impl WatchingCompiler {
+    pub fn new<P: AsRef<Path>>(paths: &[P]) -> Result<WatchingCompiler, FileCompilerError> {
+        WatchingCompiler::create(paths, None)
+    }
+
+    pub fn with_cache<P: AsRef<Path>>(paths: &[P], cache: ProgramCache) -> Result<WatchingCompiler, FileCompilerError> {
+        WatchingCompiler::create(paths, Some(cache))
+    }
+
+    fn create<P: AsRef<Path>>(paths: &[P], cache: Option<ProgramCache>) -> Result<WatchingCompiler, FileCompilerError> {
+        let paths: Vec<PathBuf> = paths.iter().map(|p| p.as_ref().to_path_buf()).collect();
+        let mut dependencies = Vec::new();
+        let program = FileCompiler::compile_with_dependencies(&paths, cache.as_ref(), &mut dependencies)?;
+
+        Ok(WatchingCompiler {
+            paths,
+            watched: watch(dependencies),
+            program,
+            cache,
+        })
+    }
+
+    pub fn program(&self) -> &Program {
+        &self.program
+    }
+
+    // Returns `Ok(true)` if the program was recompiled, and `Ok(false)` if none of the files
+    // changed. If the new sources fail to compile or link, the error is returned and the previous
+    // program is kept, so the application can keep running until the shaders are fixed.
+    pub fn reload_if_changed(&mut self) -> Result<bool, FileCompilerError> {
+        let changed = self.watched.iter().any(|&(ref path, modified)| modified_time(path) != modified);
+
+        if !changed {
+            return Ok(false);
         }
 
-        Program::link(&shaders).map_err(|e| SourceCompilerError::ProgramCreationError(e))
+        let mut dependencies = Vec::new();
+
+        match FileCompiler::compile_with_dependencies(&self.paths, self.cache.as_ref(), &mut dependencies) {
+            Ok(program) => {
+                self.program = program;
+                self.watched = watch(dependencies);
+
+                Ok(true)
+            }
+            Err(e) => {
+                // Remember the new modification times even if compilation fails. Otherwise, we'd
+                // report the same error on every call until the files change again. Besides the
+                // files the new sources depend on, we keep watching the previous ones and the root
+                // files, in case preprocessing stopped early at a broken `#include`.
+                dependencies.extend(self.watched.drain(..).map(|(path, _)| path));
+                dependencies.extend(self.paths.iter().cloned());
+                self.watched = watch(dependencies);
+
+                Err(e)
+            }
+        }
     }
 }
 
-// TODO
-// struct FileCompiler {}
-// struct WatchingCompiler {}
+fn watch(mut paths: Vec<PathBuf>) -> Vec<(PathBuf, Option<SystemTime>)> {
+    paths.sort();
+    paths.dedup();
+
+    paths.into_iter()
+        .map(|path| {
+            let modified = modified_time(&path);
+            (path, modified)
+        })
+        .collect()
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
@@ -0,0 +1,167 @@
+use gl;
+use gl::types::*;
+
+use std::ffi;
+
+use gl_object::GlObject;
+use program::Program;
+
+// Once a program is linked, OpenGL can tell us which attributes, uniforms and uniform blocks are
+// active, ie. actually used by the shaders. Anything the compiler optimized away won't show up.
+//
+// Array sizes are reported in `size`, and are 1 for variables that are not arrays. Types are the
+// GL type enums, eg. `gl::FLOAT_VEC3` or `gl::SAMPLER_2D`.
+
+#[derive(Clone, Debug)]
+pub struct ActiveAttribute {
+    pub name: String,
+    pub ty: GLenum,
+    pub size: GLint,
+    pub location: GLint,
+}
+
+#[derive(Clone, Debug)]
+pub struct ActiveUniform {
+    pub name: String,
+    pub ty: GLenum,
+    pub size: GLint,
+
+    // Uniforms that belong to a uniform block don't have a location, so this is -1 for them.
+    pub location: GLint,
+    pub block_index: Option<GLuint>,
+}
+
+#[derive(Clone, Debug)]
+pub struct UniformBlock {
+    pub name: String,
+    pub index: GLuint,
+    pub binding: GLuint,
+    pub data_size: GLint,
+    pub uniform_indices: Vec<GLuint>,
+}
+
+impl Program {
+    pub fn attributes(&self) -> Vec<ActiveAttribute> {
+        let count = self.get(gl::ACTIVE_ATTRIBUTES);
+        let max_name_len = self.get(gl::ACTIVE_ATTRIBUTE_MAX_LENGTH);
+
+        (0..count as GLuint)
+            .map(|index| {
+                let mut size = 0;
+                let mut ty = 0;
+
+                let name = read_name(max_name_len, |buf_len, len, buf| unsafe {
+                    gl::GetActiveAttrib(self.id(), index, buf_len, len, &mut size, &mut ty, buf);
+                });
+
+                ActiveAttribute {
+                    location: self.attribute_location(&name).map(|l| l as GLint).unwrap_or(-1),
+                    name,
+                    ty,
+                    size,
+                }
+            })
+            .collect()
+    }
+
+    pub fn uniforms(&self) -> Vec<ActiveUniform> {
+        let count = self.get(gl::ACTIVE_UNIFORMS);
+        let max_name_len = self.get(gl::ACTIVE_UNIFORM_MAX_LENGTH);
+
+        (0..count as GLuint)
+            .map(|index| {
+                let mut size = 0;
+                let mut ty = 0;
+                let mut block_index = -1;
+
+                let name = read_name(max_name_len, |buf_len, len, buf| unsafe {
+                    gl::GetActiveUniform(self.id(), index, buf_len, len, &mut size, &mut ty, buf);
+                });
+
+                unsafe {
+                    gl::GetActiveUniformsiv(self.id(), 1, &index, gl::UNIFORM_BLOCK_INDEX, &mut block_index);
+                }
+
+                let location = match ffi::CString::new(name.as_str()) {
+                    Ok(c_name) => unsafe { gl::GetUniformLocation(self.id(), c_name.as_ptr()) },
+                    Err(_)     => -1,
+                };
+
+                ActiveUniform {
+                    name,
+                    ty,
+                    size,
+                    location,
+                    block_index: if block_index < 0 { None } else { Some(block_index as GLuint) },
+                }
+            })
+            .collect()
+    }
+
+    pub fn uniform_blocks(&self) -> Vec<UniformBlock> {
+        let count = self.get(gl::ACTIVE_UNIFORM_BLOCKS);
+        let max_name_len = self.get(gl::ACTIVE_UNIFORM_BLOCK_MAX_NAME_LENGTH);
+
+        (0..count as GLuint)
+            .map(|index| {
+                let name = read_name(max_name_len, |buf_len, len, buf| unsafe {
+                    gl::GetActiveUniformBlockName(self.id(), index, buf_len, len, buf);
+                });
+
+                let uniform_count = self.get_block(index, gl::UNIFORM_BLOCK_ACTIVE_UNIFORMS);
+                let mut uniform_indices = vec![0 as GLint; uniform_count as usize];
+
+                if uniform_count > 0 {
+                    unsafe {
+                        gl::GetActiveUniformBlockiv(self.id(), index, gl::UNIFORM_BLOCK_ACTIVE_UNIFORM_INDICES,
+                            uniform_indices.as_mut_ptr());
+                    }
+                }
+
+                UniformBlock {
+                    name,
+                    index,
+                    binding: self.get_block(index, gl::UNIFORM_BLOCK_BINDING) as GLuint,
+                    data_size: self.get_block(index, gl::UNIFORM_BLOCK_DATA_SIZE),
+                    uniform_indices: uniform_indices.into_iter().map(|i| i as GLuint).collect(),
+                }
+            })
+            .collect()
+    }
+
+    // Returns `None` if there is no active attribute with that name.
+    pub fn attribute_location(&self, name: &str) -> Option<GLuint> {
+        let c_name = ffi::CString::new(name).ok()?;
+        let location = unsafe { gl::GetAttribLocation(self.id(), c_name.as_ptr()) };
+
+        if location < 0 { None } else { Some(location as GLuint) }
+    }
+
+    fn get(&self, pname: GLenum) -> GLint {
+        let mut value = 0;
+        unsafe { gl::GetProgramiv(self.id(), pname, &mut value); }
+
+        value
+    }
+
+    fn get_block(&self, block_index: GLuint, pname: GLenum) -> GLint {
+        let mut value = 0;
+        unsafe { gl::GetActiveUniformBlockiv(self.id(), block_index, pname, &mut value); }
+
+        value
+    }
+}
+
+// The `GetActive*` functions write the name into a buffer we provide, and tell us how many bytes
+// they wrote, not counting the NUL terminator.
+fn read_name<F>(max_len: GLint, read: F) -> String
+    where F: FnOnce(GLsizei, *mut GLsizei, *mut GLchar)
+{
+    let mut buf = vec![0u8; max_len.max(1) as usize];
+    let mut len = 0;
+
+    read(buf.len() as GLsizei, &mut len, buf.as_mut_ptr() as *mut GLchar);
+    buf.truncate(len as usize);
+
+    String::from_utf8_lossy(&buf).into_owned()
+}
@@ -0,0 +1,419 @@
+use gl;
+use gl::types::*;
+
+use std::cell::Cell;
+
+// The fixed-function state a draw call depends on: depth and stencil tests, blending, face culling,
+// polygon mode and color writes.
+//
+// Each draw declares the state it needs and applies it right before drawing:
+//
+//     let state = RenderState {
+//         depth_test: Some(Comparison::Less),
+//         cull_face: Some(Face::Back),
+//         ..RenderState::default()
+//     };
+//
+//     state.apply();
+//     vao.bind();
+//     gl::DrawArrays(...);
+//
+// `apply` remembers the last state it applied on this thread, and only makes the GL calls for the
+// fields that changed since, so applying the same state before every draw is cheap. Code that
+// changes this state behind `apply`'s back must restore it afterwards, or call `invalidate`.
+//
+// The default is OpenGL's initial state, with every test and blending disabled.
+//
+// Note that the depth, stencil and color write masks also apply to `glClear`: a draw that turns
+// depth writes off stops the next frame's clear from clearing the depth buffer, unless something
+// applies a state with depth writes on first.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RenderState {
+    // The depth test, or `None` to disable it. Fragments pass when `Comparison` holds between their
+    // depth and the one in the depth buffer.
+    pub depth_test: Option<Comparison>,
+    // Whether fragments that pass write their depth. OpenGL never writes depth while the depth test
+    // is disabled.
+    pub depth_write: bool,
+    pub stencil: Option<Stencil>,
+    pub blend: Option<Blend>,
+    // Which faces to cull, or `None` to draw both.
+    pub cull_face: Option<Face>,
+    // The winding of the triangles that face the camera.
+    pub front_face: Winding,
+    pub polygon_mode: PolygonMode,
+    // Which of the red, green, blue and alpha channels are written.
+    pub color_mask: [bool; 4],
+}
+
+impl Default for RenderState {
+    fn default() -> RenderState {
+        RenderState {
+            depth_test: None,
+            depth_write: true,
+            stencil: None,
+            blend: None,
+            cull_face: None,
+            front_face: Winding::CounterClockwise,
+            polygon_mode: PolygonMode::Fill,
+            color_mask: [true, true, true, true],
+        }
+    }
+}
+
+thread_local!(static APPLIED_STATE: Cell<Option<RenderState>> = const { Cell::new(None) });
+
+// Expands to true when `$field` differs between `$state` and `$previous`, or when nothing is known
+// about the current state.
+macro_rules! changed {
+    ($state:ident, $previous:ident, $field:ident) => {
+        $previous.map_or(true, |previous| previous.$field != $state.$field)
+    };
+}
+
+impl RenderState {
+    // Makes this the current state, changing only what differs from the last state applied on this
+    // thread. The first call after `invalidate` sets everything.
+    pub fn apply(&self) {
+        let previous = APPLIED_STATE.with(|applied| applied.get());
+
+        if previous == Some(*self) {
+            return;
+        }
+
+        unsafe { self.apply_changes(previous.as_ref()); }
+
+        APPLIED_STATE.with(|applied| applied.set(Some(*self)));
+    }
+
+    unsafe fn apply_changes(&self, previous: Option<&RenderState>) {
+        if changed!(self, previous, depth_test) {
+            match self.depth_test {
+                Some(comparison) => {
+                    gl::Enable(gl::DEPTH_TEST);
+                    gl::DepthFunc(comparison.into());
+                }
+                None => gl::Disable(gl::DEPTH_TEST),
+            }
+        }
+
+        if changed!(self, previous, depth_write) {
+            gl::DepthMask(self.depth_write as GLboolean);
+        }
+
+        if changed!(self, previous, stencil) {
+            match self.stencil {
+                Some(stencil) => {
+                    gl::Enable(gl::STENCIL_TEST);
+                    gl::StencilFunc(stencil.test.into(), stencil.reference, stencil.read_mask);
+                    gl::StencilOp(stencil.fail.into(), stencil.depth_fail.into(), stencil.pass.into());
+                    gl::StencilMask(stencil.write_mask);
+                }
+                None => {
+                    // Restore the write mask too, or clearing the stencil buffer would keep using
+                    // the mask of the last stencil state.
+                    gl::Disable(gl::STENCIL_TEST);
+                    gl::StencilMask(!0);
+                }
+            }
+        }
+
+        if changed!(self, previous, blend) {
+            match self.blend {
+                Some(blend) => {
+                    gl::Enable(gl::BLEND);
+                    gl::BlendEquationSeparate(blend.color.operation.into(), blend.alpha.operation.into());
+                    gl::BlendFuncSeparate(
+                        blend.color.source.into(), blend.color.destination.into(),
+                        blend.alpha.source.into(), blend.alpha.destination.into(),
+                    );
+                    gl::BlendColor(blend.constant[0], blend.constant[1], blend.constant[2], blend.constant[3]);
+                }
+                None => gl::Disable(gl::BLEND),
+            }
+        }
+
+        if changed!(self, previous, cull_face) {
+            match self.cull_face {
+                Some(face) => {
+                    gl::Enable(gl::CULL_FACE);
+                    gl::CullFace(face.into());
+                }
+                None => gl::Disable(gl::CULL_FACE),
+            }
+        }
+
+        if changed!(self, previous, front_face) {
+            gl::FrontFace(self.front_face.into());
+        }
+
+        // Core profiles only allow setting the mode of both faces at once.
+        if changed!(self, previous, polygon_mode) {
+            gl::PolygonMode(gl::FRONT_AND_BACK, self.polygon_mode.into());
+        }
+
+        if changed!(self, previous, color_mask) {
+            let mask = self.color_mask;
+            gl::ColorMask(mask[0] as GLboolean, mask[1] as GLboolean, mask[2] as GLboolean, mask[3] as GLboolean);
+        }
+    }
+}
+
+// Forgets the last applied state, so the next `RenderState::apply` sets every field. Call this
+// after changing the state directly, or when making another context current on this thread.
+pub fn invalidate() {
+    APPLIED_STATE.with(|applied| applied.set(None));
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Comparison {
+    Never,
+    Less,
+    Equal,
+    LessOrEqual,
+    Greater,
+    NotEqual,
+    GreaterOrEqual,
+    Always,
+}
+
+impl From<Comparison> for GLenum {
+    fn from(comparison: Comparison) -> Self {
+        match comparison {
+            Comparison::Never          => gl::NEVER,
+            Comparison::Less           => gl::LESS,
+            Comparison::Equal          => gl::EQUAL,
+            Comparison::LessOrEqual    => gl::LEQUAL,
+            Comparison::Greater        => gl::GREATER,
+            Comparison::NotEqual       => gl::NOTEQUAL,
+            Comparison::GreaterOrEqual => gl::GEQUAL,
+            Comparison::Always         => gl::ALWAYS,
+        }
+    }
+}
+
+// The stencil test, applied to both front and back faces.
+//
+// A fragment passes when `test` holds between `reference & read_mask` and the stored value
+// `& read_mask`. The stored value is then updated with `fail` if the stencil test failed,
+// `depth_fail` if the depth test failed, and `pass` otherwise. Only the bits in `write_mask` are
+// written.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Stencil {
+    pub test: Comparison,
+    pub reference: GLint,
+    pub read_mask: GLuint,
+    pub write_mask: GLuint,
+    pub fail: StencilOp,
+    pub depth_fail: StencilOp,
+    pub pass: StencilOp,
+}
+
+impl Default for Stencil {
+    fn default() -> Stencil {
+        Stencil {
+            test: Comparison::Always,
+            reference: 0,
+            read_mask: !0,
+            write_mask: !0,
+            fail: StencilOp::Keep,
+            depth_fail: StencilOp::Keep,
+            pass: StencilOp::Keep,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StencilOp {
+    Keep,
+    Zero,
+    // Write the reference value.
+    Replace,
+    // Increment, or decrement, clamping at the largest value and at 0.
+    Increment,
+    Decrement,
+    // Increment, or decrement, wrapping around.
+    IncrementWrap,
+    DecrementWrap,
+    Invert,
+}
+
+impl From<StencilOp> for GLenum {
+    fn from(op: StencilOp) -> Self {
+        match op {
+            StencilOp::Keep          => gl::KEEP,
+            StencilOp::Zero          => gl::ZERO,
+            StencilOp::Replace       => gl::REPLACE,
+            StencilOp::Increment     => gl::INCR,
+            StencilOp::Decrement     => gl::DECR,
+            StencilOp::IncrementWrap => gl::INCR_WRAP,
+            StencilOp::DecrementWrap => gl::DECR_WRAP,
+            StencilOp::Invert        => gl::INVERT,
+        }
+    }
+}
+
+// How fragments are combined with the color already in the framebuffer. The color and alpha
+// channels are blended separately.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Blend {
+    pub color: BlendEquation,
+    pub alpha: BlendEquation,
+    // The color used by the `Constant*` factors.
+    pub constant: [f32; 4],
+}
+
+impl Blend {
+    // Classic transparency: `source * source_alpha + destination * (1 - source_alpha)`.
+    pub fn alpha() -> Blend {
+        Blend::with_factors(BlendFactor::SourceAlpha, BlendFactor::OneMinusSourceAlpha)
+    }
+
+    // Transparency for colors already multiplied by their alpha.
+    pub fn premultiplied_alpha() -> Blend {
+        Blend::with_factors(BlendFactor::One, BlendFactor::OneMinusSourceAlpha)
+    }
+
+    // Adds the fragment to the framebuffer, eg. for lights and particles.
+    pub fn additive() -> Blend {
+        Blend::with_factors(BlendFactor::One, BlendFactor::One)
+    }
+
+    // Adds `source * fragment` and `destination * framebuffer`, for both color and alpha.
+    pub fn with_factors(source: BlendFactor, destination: BlendFactor) -> Blend {
+        let equation = BlendEquation {
+            operation: BlendOperation::Add,
+            source,
+            destination,
+        };
+
+        Blend {
+            color: equation,
+            alpha: equation,
+            constant: [0.0, 0.0, 0.0, 0.0],
+        }
+    }
+}
+
+// Combines `source * fragment` and `destination * framebuffer` with `operation`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BlendEquation {
+    pub operation: BlendOperation,
+    pub source: BlendFactor,
+    pub destination: BlendFactor,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BlendOperation {
+    Add,
+    // Fragment minus framebuffer.
+    Subtract,
+    // Framebuffer minus fragment.
+    ReverseSubtract,
+    // `Min` and `Max` ignore the factors.
+    Min,
+    Max,
+}
+
+impl From<BlendOperation> for GLenum {
+    fn from(operation: BlendOperation) -> Self {
+        match operation {
+            BlendOperation::Add             => gl::FUNC_ADD,
+            BlendOperation::Subtract        => gl::FUNC_SUBTRACT,
+            BlendOperation::ReverseSubtract => gl::FUNC_REVERSE_SUBTRACT,
+            BlendOperation::Min             => gl::MIN,
+            BlendOperation::Max             => gl::MAX,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BlendFactor {
+    Zero,
+    One,
+    SourceColor,
+    OneMinusSourceColor,
+    DestinationColor,
+    OneMinusDestinationColor,
+    SourceAlpha,
+    OneMinusSourceAlpha,
+    DestinationAlpha,
+    OneMinusDestinationAlpha,
+    ConstantColor,
+    OneMinusConstantColor,
+    ConstantAlpha,
+    OneMinusConstantAlpha,
+    SourceAlphaSaturate,
+}
+
+impl From<BlendFactor> for GLenum {
+    fn from(factor: BlendFactor) -> Self {
+        match factor {
+            BlendFactor::Zero                     => gl::ZERO,
+            BlendFactor::One                      => gl::ONE,
+            BlendFactor::SourceColor              => gl::SRC_COLOR,
+            BlendFactor::OneMinusSourceColor      => gl::ONE_MINUS_SRC_COLOR,
+            BlendFactor::DestinationColor         => gl::DST_COLOR,
+            BlendFactor::OneMinusDestinationColor => gl::ONE_MINUS_DST_COLOR,
+            BlendFactor::SourceAlpha              => gl::SRC_ALPHA,
+            BlendFactor::OneMinusSourceAlpha      => gl::ONE_MINUS_SRC_ALPHA,
+            BlendFactor::DestinationAlpha         => gl::DST_ALPHA,
+            BlendFactor::OneMinusDestinationAlpha => gl::ONE_MINUS_DST_ALPHA,
+            BlendFactor::ConstantColor            => gl::CONSTANT_COLOR,
+            BlendFactor::OneMinusConstantColor    => gl::ONE_MINUS_CONSTANT_COLOR,
+            BlendFactor::ConstantAlpha            => gl::CONSTANT_ALPHA,
+            BlendFactor::OneMinusConstantAlpha    => gl::ONE_MINUS_CONSTANT_ALPHA,
+            BlendFactor::SourceAlphaSaturate      => gl::SRC_ALPHA_SATURATE,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Face {
+    Front,
+    Back,
+    FrontAndBack,
+}
+
+impl From<Face> for GLenum {
+    fn from(face: Face) -> Self {
+        match face {
+            Face::Front        => gl::FRONT,
+            Face::Back         => gl::BACK,
+            Face::FrontAndBack => gl::FRONT_AND_BACK,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Winding {
+    Clockwise,
+    CounterClockwise,
+}
+
+impl From<Winding> for GLenum {
+    fn from(winding: Winding) -> Self {
+        match winding {
+            Winding::Clockwise        => gl::CW,
+            Winding::CounterClockwise => gl::CCW,
+        }
+    }
+}
+
+// How triangles are rasterized: as their corners, their edges (wireframe), or filled.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PolygonMode {
+    Point,
+    Line,
+    Fill,
+}
+
+impl From<PolygonMode> for GLenum {
+    fn from(mode: PolygonMode) -> Self {
+        match mode {
+            PolygonMode::Point => gl::POINT,
+            PolygonMode::Line  => gl::LINE,
+            PolygonMode::Fill  => gl::FILL,
+        }
+    }
+}
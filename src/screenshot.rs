@@ -0,0 +1,184 @@
+use gl;
+use gl::types::*;
+use glutin;
+use image;
+
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use glutin::{ElementState, Event, VirtualKeyCode};
+
+use framebuffer::Framebuffer;
+use gl_object::GlObject;
+
+// The pixels of a framebuffer, as 8-bit RGB with the top row first, like image files.
+pub struct Screenshot {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+}
+
+impl Screenshot {
+    // Reads the default framebuffer, ie. the window (or headless context). With a double-buffered
+    // window, this must happen after drawing and before `swap_buffers`, while the frame is still
+    // in the back buffer.
+    pub fn capture(width: u32, height: u32) -> Screenshot {
+        Screenshot::read(0, width, height)
+    }
+
+    // Reads the first color attachment of `framebuffer`.
+    pub fn capture_framebuffer(framebuffer: &Framebuffer, width: u32, height: u32) -> Screenshot {
+        Screenshot::read(framebuffer.id(), width, height)
+    }
+
+    fn read(framebuffer: GLuint, width: u32, height: u32) -> Screenshot {
+        // We read RGB, without the alpha channel: the window's alpha is often meaningless, and
+        // would make the PNG (partly) transparent. Rows of 3-byte pixels aren't necessarily a
+        // multiple of 4 bytes long, so we ask for them tightly packed.
+        let row_len = width as usize * 3;
+        let mut pixels = vec![0u8; row_len * height as usize];
+
+        unsafe {
+            let mut previous_framebuffer = 0;
+            let mut previous_alignment = 0;
+            gl::GetIntegerv(gl::READ_FRAMEBUFFER_BINDING, &mut previous_framebuffer);
+            gl::GetIntegerv(gl::PACK_ALIGNMENT, &mut previous_alignment);
+
+            gl::BindFramebuffer(gl::READ_FRAMEBUFFER, framebuffer);
+            gl::PixelStorei(gl::PACK_ALIGNMENT, 1);
+
+            gl::ReadPixels(0, 0, width as GLsizei, height as GLsizei, gl::RGB, gl::UNSIGNED_BYTE,
+                pixels.as_mut_ptr() as *mut GLvoid);
+
+            gl::PixelStorei(gl::PACK_ALIGNMENT, previous_alignment);
+            gl::BindFramebuffer(gl::READ_FRAMEBUFFER, previous_framebuffer as GLuint);
+        }
+
+        // OpenGL returns the bottom row first.
+        let pixels = if row_len > 0 {
+            pixels.chunks(row_len).rev().flat_map(|row| row.iter().cloned()).collect()
+        } else {
+            pixels
+        };
+
+        Screenshot {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    #[inline]
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    #[inline]
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    #[inline]
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    // The color at (`x`, `y`), counting from the top left.
+    pub fn pixel(&self, x: u32, y: u32) -> [u8; 3] {
+        let i = (y as usize * self.width as usize + x as usize) * 3;
+        [self.pixels[i], self.pixels[i + 1], self.pixels[i + 2]]
+    }
+
+    // Saves the screenshot as a PNG, creating the parent directories if needed.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let path = path.as_ref();
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        image::save_buffer(path, &self.pixels, self.width, self.height, image::ColorType::RGB(8))
+    }
+}
+
+// Lets an example save screenshots of its window, either when a key is pressed (F12 by default)
+// or after it has drawn a given number of frames.
+//
+// The frame count comes from the `SCREENSHOT_AFTER_FRAMES` environment variable. Screenshots are
+// saved to the `SCREENSHOT_DIR` directory (`screenshots` by default) as `<name>-<frame>.png`.
+//
+//     let mut screenshots = ScreenshotTrigger::new("06_textures");
+//
+//     for event in window.poll_events() {
+//         screenshots.handle_event(&event);
+//     }
+//
+//     // ... draw ...
+//
+//     if screenshots.end_frame(&window) {
+//         break;
+//     }
+//     window.swap_buffers().unwrap();
+pub struct ScreenshotTrigger {
+    name: String,
+    dir: PathBuf,
+    key: VirtualKeyCode,
+    after_frames: Option<u64>,
+    frames: u64,
+    requested: bool,
+}
+
+impl ScreenshotTrigger {
+    pub fn new(name: &str) -> ScreenshotTrigger {
+        let after_frames = env::var("SCREENSHOT_AFTER_FRAMES").ok().and_then(|frames| frames.parse().ok());
+        let dir = env::var_os("SCREENSHOT_DIR").map_or_else(|| PathBuf::from("screenshots"), PathBuf::from);
+
+        ScreenshotTrigger {
+            name: name.to_string(),
+            dir,
+            key: VirtualKeyCode::F12,
+            after_frames,
+            frames: 0,
+            requested: false,
+        }
+    }
+
+    pub fn with_key(mut self, key: VirtualKeyCode) -> ScreenshotTrigger {
+        self.key = key;
+        self
+    }
+
+    pub fn handle_event(&mut self, event: &Event) {
+        if let Event::KeyboardInput(ElementState::Pressed, _, Some(key)) = *event {
+            if key == self.key {
+                self.requested = true;
+            }
+        }
+    }
+
+    // Call this once per frame, after drawing and before swapping buffers. Returns true once the
+    // screenshot requested by `SCREENSHOT_AFTER_FRAMES` has been taken, so the example can exit.
+    //
+    // Examples that only redraw on window events count one frame per event.
+    pub fn end_frame(&mut self, window: &glutin::Window) -> bool {
+        self.frames += 1;
+
+        let scheduled = self.after_frames == Some(self.frames);
+
+        if self.requested || scheduled {
+            self.requested = false;
+
+            let (width, height) = window.get_inner_size_pixels().unwrap_or((0, 0));
+            let path = self.dir.join(format!("{}-{}.png", self.name, self.frames));
+
+            match Screenshot::capture(width, height).save(&path) {
+                Ok(()) => println!("Saved screenshot to {}", path.display()),
+                Err(e) => println!("Couldn't save screenshot to {}: {}", path.display(), e),
+            }
+        }
+
+        scheduled
+    }
+}
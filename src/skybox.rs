@@ -0,0 +1,137 @@
+use gl;
+use gl::types::*;
+
+use buffer::{Buffer, BufferTarget, BufferUsage};
+use program::{Program, ShaderType, SourceCompiler, SourceCompilerError};
+use texture::TextureCube;
+use uniform::Sampler;
+use vertex_array::{VertexArray, VertexAttribute, VertexLayout};
+
+const VERTEX_SHADER_SRC: &'static str = r#"
+#version 330 core
+
+layout (location = 0) in vec3 vbo_position;
+
+uniform mat4 view;
+uniform mat4 projection;
+
+out vec3 direction;
+
+void main() {
+    direction = vbo_position;
+
+    // Dropping the translation from the view matrix keeps the camera at the center of the cube, so
+    // the sky looks infinitely far away.
+    vec4 position = projection * mat4(mat3(view)) * vec4(vbo_position, 1.0);
+
+    // With z = w, the depth after the perspective divide is 1.0, ie. the far plane.
+    gl_Position = position.xyww;
+}
+"#;
+
+const FRAGMENT_SHADER_SRC: &'static str = r#"
+#version 330 core
+
+in vec3 direction;
+
+uniform samplerCube skybox;
+
+out vec4 frag_color;
+
+void main() {
+    frag_color = texture(skybox, direction);
+}
+"#;
+
+// The corners of a cube around the origin. Corner `i` has positive x if bit 0 of `i` is set,
+// positive y for bit 1 and positive z for bit 2.
+const VERTICES: [[GLfloat; 3]; 8] = [
+    [-1.0, -1.0, -1.0],
+    [ 1.0, -1.0, -1.0],
+    [-1.0,  1.0, -1.0],
+    [ 1.0,  1.0, -1.0],
+    [-1.0, -1.0,  1.0],
+    [ 1.0, -1.0,  1.0],
+    [-1.0,  1.0,  1.0],
+    [ 1.0,  1.0,  1.0],
+];
+
+// We look at the cube from the inside, so the triangles are counter-clockwise when seen from the
+// origin. That way, face culling doesn't hide the skybox.
+const INDICES: [GLuint; 36] = [
+    1, 5, 7, 1, 7, 3, // +X
+    4, 0, 2, 4, 2, 6, // -X
+    7, 6, 2, 7, 2, 3, // +Y
+    1, 0, 4, 1, 4, 5, // -Y
+    5, 4, 6, 5, 6, 7, // +Z
+    0, 1, 3, 0, 3, 2, // -Z
+];
+
+// Draws a cubemap as the background of the scene.
+//
+// The skybox is drawn at the far plane, so it should be drawn after the rest of the scene: the
+// depth test then skips every pixel already covered by something else.
+pub struct Skybox {
+    program: Program,
+    vao: VertexArray,
+    // Referenced by the vertex array, so they must live as long as it.
+    _vbo: Buffer<[GLfloat; 3]>,
+    _ebo: Buffer<GLuint>,
+}
+
+impl Skybox {
+    pub fn new() -> Result<Skybox, SourceCompilerError> {
+        let program = SourceCompiler::compile(&[
+            (ShaderType::Vertex, VERTEX_SHADER_SRC),
+            (ShaderType::Fragment, FRAGMENT_SHADER_SRC),
+        ])?;
+
+        let vbo = Buffer::new(BufferTarget::Vertex, &VERTICES, BufferUsage::StaticDraw);
+        let ebo = Buffer::new(BufferTarget::Index, &INDICES, BufferUsage::StaticDraw);
+
+        let vao = VertexArray::new();
+        vao.attach_vertex_buffer(&vbo, &VertexLayout::new().with_attribute(VertexAttribute::float(0, 3)));
+        vao.attach_index_buffer(&ebo);
+
+        // Filter across the edges between faces, instead of clamping at each face's edge, which
+        // leaves visible seams. This is global state, but there's no reason to ever turn it off.
+        unsafe { gl::Enable(gl::TEXTURE_CUBE_MAP_SEAMLESS); }
+
+        Ok(Skybox {
+            program,
+            vao,
+            _vbo: vbo,
+            _ebo: ebo,
+        })
+    }
+
+    // Draws `texture` around the camera. `view` and `projection` are the scene's (column-major)
+    // matrices; only the rotation part of `view` is used. The texture is bound to unit 0.
+    pub fn draw(&self, texture: &TextureCube, view: [[f32; 4]; 4], projection: [[f32; 4]; 4]) {
+        self.program.set_uniform("view", view).unwrap();
+        self.program.set_uniform("projection", projection).unwrap();
+        self.program.set_uniform("skybox", Sampler(0)).unwrap();
+
+        texture.bind(0);
+
+        unsafe {
+            // The skybox's depth is exactly 1.0, which the default `LESS` test rejects when the
+            // depth buffer was cleared to 1.0. We don't write depth either, so the skybox never
+            // hides anything drawn after it.
+            let mut depth_func = 0;
+            let mut depth_mask = 0;
+            gl::GetIntegerv(gl::DEPTH_FUNC, &mut depth_func);
+            gl::GetBooleanv(gl::DEPTH_WRITEMASK, &mut depth_mask);
+
+            gl::DepthFunc(gl::LEQUAL);
+            gl::DepthMask(gl::FALSE);
+
+            self.vao.bind();
+            gl::DrawElements(gl::TRIANGLES, INDICES.len() as GLsizei, gl::UNSIGNED_INT, 0 as *const GLvoid);
+            self.vao.unbind();
+
+            gl::DepthFunc(depth_func as GLenum);
+            gl::DepthMask(depth_mask);
+        }
+    }
+}
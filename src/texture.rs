@@ -0,0 +1,1274 @@
+use gl;
+use gl::types::*;
+use image;
+use png;
+
+use std::borrow::Cow;
+use std::fs;
+use std::io;
+use std::mem;
+use std::marker::PhantomData;
+use std::path::Path;
+use std::ptr;
+
+use image::{ColorType, DynamicImage, GenericImage};
+
+use gl_object::{GlObject, Handle};
+
+#[derive(Debug)]
+pub enum TextureCreationError {
+    ImageError(image::ImageError),
+    UnsupportedColorType(ColorType),
+    InvalidDataLength { expected: usize, actual: usize },
+    NonSquareCubeFace { width: u32, height: u32 },
+    MismatchedCubeFaces,
+    InvalidCrossLayout { width: u32, height: u32 },
+    MismatchedLayers,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Wrap {
+    Repeat,
+    MirroredRepeat,
+    ClampToEdge,
+    ClampToBorder,
+}
+
+impl From<Wrap> for GLenum {
+    fn from(wrap: Wrap) -> Self {
+        match wrap {
+            Wrap::Repeat         => gl::REPEAT,
+            Wrap::MirroredRepeat => gl::MIRRORED_REPEAT,
+            Wrap::ClampToEdge    => gl::CLAMP_TO_EDGE,
+            Wrap::ClampToBorder  => gl::CLAMP_TO_BORDER,
+        }
+    }
+}
+
+// Magnification filter.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Filter {
+    Nearest,
+    Linear,
+}
+
+impl From<Filter> for GLenum {
+    fn from(filter: Filter) -> Self {
+        match filter {
+            Filter::Nearest => gl::NEAREST,
+            Filter::Linear  => gl::LINEAR,
+        }
+    }
+}
+
+// Minification filter. The `*Mipmap*` variants pick (and possibly blend) the closest mipmap levels
+// first, so they need the texture to have mipmaps.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MinFilter {
+    Nearest,
+    Linear,
+    NearestMipmapNearest,
+    LinearMipmapNearest,
+    NearestMipmapLinear,
+    LinearMipmapLinear,
+}
+
+impl From<MinFilter> for GLenum {
+    fn from(filter: MinFilter) -> Self {
+        match filter {
+            MinFilter::Nearest              => gl::NEAREST,
+            MinFilter::Linear               => gl::LINEAR,
+            MinFilter::NearestMipmapNearest => gl::NEAREST_MIPMAP_NEAREST,
+            MinFilter::LinearMipmapNearest  => gl::LINEAR_MIPMAP_NEAREST,
+            MinFilter::NearestMipmapLinear  => gl::NEAREST_MIPMAP_LINEAR,
+            MinFilter::LinearMipmapLinear   => gl::LINEAR_MIPMAP_LINEAR,
+        }
+    }
+}
+
+impl MinFilter {
+    // A texture without mipmaps is incomplete (and samples as black) if its minification filter
+    // uses mipmaps, so we fall back to the equivalent filter without them.
+    fn without_mipmaps(&self) -> MinFilter {
+        match *self {
+            MinFilter::Nearest | MinFilter::NearestMipmapNearest | MinFilter::NearestMipmapLinear => MinFilter::Nearest,
+            MinFilter::Linear  | MinFilter::LinearMipmapNearest  | MinFilter::LinearMipmapLinear  => MinFilter::Linear,
+        }
+    }
+}
+
+// How pixel data is laid out in memory (`format` and `ty`), and how the GPU should store it
+// (`internal_format`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PixelFormat {
+    pub internal_format: GLenum,
+    pub format: GLenum,
+    pub ty: GLenum,
+}
+
+impl PixelFormat {
+    // Picks the formats matching an image's color type. With `srgb`, 8-bit color images are stored
+    // as sRGB, so that the GPU converts them to linear colors when sampling. Grayscale and 16-bit
+    // images have no sRGB formats and are always linear.
+    pub fn from_color_type(color: ColorType, srgb: bool) -> Option<PixelFormat> {
+        let (internal_format, format, ty) = match color {
+            ColorType::Gray(8)   => (gl::R8, gl::RED, gl::UNSIGNED_BYTE),
+            ColorType::GrayA(8)  => (gl::RG8, gl::RG, gl::UNSIGNED_BYTE),
+            ColorType::RGB(8)    => (if srgb { gl::SRGB8 } else { gl::RGB8 }, gl::RGB, gl::UNSIGNED_BYTE),
+            ColorType::RGBA(8)   => (if srgb { gl::SRGB8_ALPHA8 } else { gl::RGBA8 }, gl::RGBA, gl::UNSIGNED_BYTE),
+            ColorType::Gray(16)  => (gl::R16, gl::RED, gl::UNSIGNED_SHORT),
+            ColorType::GrayA(16) => (gl::RG16, gl::RG, gl::UNSIGNED_SHORT),
+            ColorType::RGB(16)   => (gl::RGB16, gl::RGB, gl::UNSIGNED_SHORT),
+            ColorType::RGBA(16)  => (gl::RGBA16, gl::RGBA, gl::UNSIGNED_SHORT),
+            _                    => return None,
+        };
+
+        Some(PixelFormat {
+            internal_format,
+            format,
+            ty,
+        })
+    }
+
+    // The size of one pixel, in bytes.
+    pub fn pixel_size(&self) -> usize {
+        components(self.format) * type_size(self.ty)
+    }
+
+    // Grayscale images are uploaded to the red (and green) channels. Swizzling makes them sample
+    // as gray (with alpha), like the luminance formats core profiles no longer have.
+    fn swizzle(&self) -> Option<[GLint; 4]> {
+        match self.format {
+            gl::RED => Some([gl::RED as GLint, gl::RED as GLint, gl::RED as GLint, gl::ONE as GLint]),
+            gl::RG  => Some([gl::RED as GLint, gl::RED as GLint, gl::RED as GLint, gl::GREEN as GLint]),
+            _       => None,
+        }
+    }
+}
+
+// Sampling parameters shared by every kind of texture.
+#[derive(Clone, Copy, Debug)]
+pub struct TextureParameters {
+    pub wrap_s: Wrap,
+    pub wrap_t: Wrap,
+    pub wrap_r: Wrap,
+    pub min_filter: MinFilter,
+    pub mag_filter: Filter,
+    pub mipmaps: bool,
+    pub border_color: [f32; 4],
+}
+
+impl Default for TextureParameters {
+    fn default() -> TextureParameters {
+        TextureParameters {
+            wrap_s: Wrap::Repeat,
+            wrap_t: Wrap::Repeat,
+            wrap_r: Wrap::Repeat,
+            min_filter: MinFilter::LinearMipmapLinear,
+            mag_filter: Filter::Linear,
+            mipmaps: true,
+            border_color: [0.0, 0.0, 0.0, 0.0],
+        }
+    }
+}
+
+impl TextureParameters {
+    // Applies the parameters to the texture bound to `target`. This must be called after the image
+    // data is uploaded, so that the mipmaps are generated from it.
+    fn apply(&self, target: GLenum) {
+        self.apply_sampling(target);
+
+        if self.mipmaps {
+            unsafe { gl::GenerateMipmap(target); }
+        }
+    }
+
+    // Applies the parameters without generating mipmaps, for textures that have no data yet.
+    fn apply_sampling(&self, target: GLenum) {
+        let min_filter = if self.mipmaps { self.min_filter } else { self.min_filter.without_mipmaps() };
+
+        unsafe {
+            gl::TexParameteri(target, gl::TEXTURE_WRAP_S, GLenum::from(self.wrap_s) as GLint);
+            gl::TexParameteri(target, gl::TEXTURE_WRAP_T, GLenum::from(self.wrap_t) as GLint);
+            gl::TexParameteri(target, gl::TEXTURE_WRAP_R, GLenum::from(self.wrap_r) as GLint);
+            gl::TexParameteri(target, gl::TEXTURE_MIN_FILTER, GLenum::from(min_filter) as GLint);
+            gl::TexParameteri(target, gl::TEXTURE_MAG_FILTER, GLenum::from(self.mag_filter) as GLint);
+            gl::TexParameterfv(target, gl::TEXTURE_BORDER_COLOR, self.border_color.as_ptr());
+        }
+    }
+}
+
+// Builds textures of type `T`. The sampling parameters are the same for every kind of texture, and
+// each texture type adds its own methods to load the image data.
+//
+//     let texture = Texture2D::builder()
+//         .wrap(Wrap::ClampToEdge)
+//         .filters(MinFilter::Nearest, Filter::Nearest)
+//         .mipmaps(false)
+//         .from_file("assets/textures/container.jpg")?;
+pub struct TextureBuilder<T> {
+    parameters: TextureParameters,
+    internal_format: Option<GLenum>,
+    srgb: bool,
+    flip_vertically: bool,
+    _marker: PhantomData<T>,
+}
+
+impl<T> TextureBuilder<T> {
+    fn new() -> TextureBuilder<T> {
+        TextureBuilder {
+            parameters: TextureParameters::default(),
+            internal_format: None,
+            srgb: false,
+            flip_vertically: false,
+            _marker: PhantomData,
+        }
+    }
+
+    // Sets the wrap mode for all coordinates.
+    pub fn wrap(mut self, wrap: Wrap) -> TextureBuilder<T> {
+        self.parameters.wrap_s = wrap;
+        self.parameters.wrap_t = wrap;
+        self.parameters.wrap_r = wrap;
+        self
+    }
+
+    pub fn wrap_s(mut self, wrap: Wrap) -> TextureBuilder<T> {
+        self.parameters.wrap_s = wrap;
+        self
+    }
+
+    pub fn wrap_t(mut self, wrap: Wrap) -> TextureBuilder<T> {
+        self.parameters.wrap_t = wrap;
+        self
+    }
+
+    pub fn wrap_r(mut self, wrap: Wrap) -> TextureBuilder<T> {
+        self.parameters.wrap_r = wrap;
+        self
+    }
+
+    pub fn filters(mut self, min_filter: MinFilter, mag_filter: Filter) -> TextureBuilder<T> {
+        self.parameters.min_filter = min_filter;
+        self.parameters.mag_filter = mag_filter;
+        self
+    }
+
+    pub fn min_filter(mut self, filter: MinFilter) -> TextureBuilder<T> {
+        self.parameters.min_filter = filter;
+        self
+    }
+
+    pub fn mag_filter(mut self, filter: Filter) -> TextureBuilder<T> {
+        self.parameters.mag_filter = filter;
+        self
+    }
+
+    pub fn mipmaps(mut self, mipmaps: bool) -> TextureBuilder<T> {
+        self.parameters.mipmaps = mipmaps;
+        self
+    }
+
+    // Only used with `Wrap::ClampToBorder`.
+    pub fn border_color(mut self, color: [f32; 4]) -> TextureBuilder<T> {
+        self.parameters.border_color = color;
+        self
+    }
+
+    // Overrides the format the texture is stored in on the GPU, eg. `gl::R8` to keep a single
+    // channel. By default, it is picked to match the image data.
+    pub fn internal_format(mut self, internal_format: GLenum) -> TextureBuilder<T> {
+        self.internal_format = Some(internal_format);
+        self
+    }
+
+    // Stores 8-bit color images as sRGB. Use this for textures holding colors (as opposed to eg.
+    // normal maps), when the shader works in linear space.
+    pub fn srgb(mut self, srgb: bool) -> TextureBuilder<T> {
+        self.srgb = srgb;
+        self
+    }
+
+    // Image files store their rows from top to bottom, but OpenGL expects the bottom row first, so
+    // images come out upside down. With this, rows are flipped before uploading, and a texture
+    // coordinate of (0, 0) is the bottom left of the image as expected.
+    pub fn flip_vertically(mut self, flip: bool) -> TextureBuilder<T> {
+        self.flip_vertically = flip;
+        self
+    }
+
+    // The pixel data to upload, flipped if needed. `data` holds one or more images of `height` rows
+    // of `row_len` values each, and each image is flipped on its own.
+    fn rows<'a, D: Clone>(&self, data: &'a [D], row_len: usize, height: usize) -> Cow<'a, [D]> {
+        rows(self.flip_vertically, data, row_len, height)
+    }
+
+    fn pixel_format(&self, color: ColorType) -> Result<PixelFormat, TextureCreationError> {
+        let mut pixel_format = PixelFormat::from_color_type(color, self.srgb)
+            .ok_or(TextureCreationError::UnsupportedColorType(color))?;
+
+        if let Some(internal_format) = self.internal_format {
+            pixel_format.internal_format = internal_format;
+        }
+
+        Ok(pixel_format)
+    }
+}
+
+pub struct Texture2D {
+    id: Handle,
+    width: u32,
+    height: u32,
+}
+
+impl Drop for Texture2D {
+    fn drop(&mut self) {
+        unsafe { gl::DeleteTextures(1, &self.id); }
+    }
+}
+
+impl GlObject for Texture2D {
+    #[inline]
+    fn id(&self) -> Handle {
+        self.id
+    }
+}
+
+impl Texture2D {
+    pub fn builder() -> TextureBuilder<Texture2D> {
+        TextureBuilder::new()
+    }
+
+    #[inline]
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    #[inline]
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    // Binds the texture to texture unit `unit`, ie. `gl::TEXTURE0 + unit`. Use `Sampler(unit)` to
+    // point a sampler uniform at it.
+    pub fn bind(&self, unit: u32) {
+        unsafe {
+            gl::ActiveTexture(gl::TEXTURE0 + unit);
+            gl::BindTexture(gl::TEXTURE_2D, self.id);
+        }
+    }
+
+    pub fn generate_mipmaps(&self) {
+        generate_mipmaps(gl::TEXTURE_2D, self.id);
+    }
+}
+
+impl TextureBuilder<Texture2D> {
+    // Allocates an uninitialized texture, eg. to render to through a `Framebuffer`. The internal
+    // format defaults to `gl::RGBA8`; use eg. `gl::DEPTH_COMPONENT24` for a depth texture.
+    //
+    // Rendering only fills the base level, so either turn mipmaps off or call `generate_mipmaps`
+    // after rendering.
+    pub fn with_size(&self, width: u32, height: u32) -> Texture2D {
+        let internal_format = self.internal_format.unwrap_or(gl::RGBA8);
+        let (format, ty) = upload_format(internal_format);
+
+        let mut id = 0;
+        unsafe {
+            gl::GenTextures(1, &mut id);
+            gl::BindTexture(gl::TEXTURE_2D, id);
+
+            gl::TexImage2D(gl::TEXTURE_2D, 0, internal_format as GLint, width as GLsizei, height as GLsizei, 0,
+                format, ty, ptr::null());
+        }
+
+        self.parameters.apply_sampling(gl::TEXTURE_2D);
+
+        unsafe { gl::BindTexture(gl::TEXTURE_2D, 0); }
+
+        Texture2D {
+            id,
+            width,
+            height,
+        }
+    }
+
+    pub fn from_file<P: AsRef<Path>>(&self, path: P) -> Result<Texture2D, TextureCreationError> {
+        let image = load_image(path.as_ref())?;
+
+        self.create_from_loaded_image(&image)
+    }
+
+    pub fn from_image(&self, image: &DynamicImage) -> Result<Texture2D, TextureCreationError> {
+        let (width, height) = image.dimensions();
+        let pixel_format = self.pixel_format(image.color())?;
+
+        self.from_raw(width, height, pixel_format, &image.raw_pixels())
+    }
+
+    fn create_from_loaded_image(&self, image: &LoadedImage) -> Result<Texture2D, TextureCreationError> {
+        let pixel_format = self.pixel_format(image.color)?;
+
+        self.from_raw(image.width, image.height, pixel_format, &image.pixels)
+    }
+
+    // Creates a texture from raw pixel data laid out as `pixel_format` says. Rows are expected to be
+    // tightly packed, and are flipped too if `flip_vertically` is set.
+    pub fn from_raw(&self, width: u32, height: u32, pixel_format: PixelFormat, data: &[u8])
+        -> Result<Texture2D, TextureCreationError>
+    {
+        let expected = width as usize * height as usize * pixel_format.pixel_size();
+
+        if data.len() != expected {
+            return Err(TextureCreationError::InvalidDataLength { expected, actual: data.len() });
+        }
+
+        let row_len = width as usize * pixel_format.pixel_size();
+        let data = self.rows(data, row_len, height as usize);
+
+        let mut id = 0;
+        unsafe {
+            gl::GenTextures(1, &mut id);
+            gl::BindTexture(gl::TEXTURE_2D, id);
+
+            with_unpack_alignment(row_len, || {
+                gl::TexImage2D(gl::TEXTURE_2D, 0, pixel_format.internal_format as GLint,
+                    width as GLsizei, height as GLsizei, 0, pixel_format.format, pixel_format.ty,
+                    data.as_ptr() as *const GLvoid);
+            });
+        }
+
+        apply_swizzle(gl::TEXTURE_2D, &pixel_format);
+        self.parameters.apply(gl::TEXTURE_2D);
+
+        unsafe { gl::BindTexture(gl::TEXTURE_2D, 0); }
+
+        Ok(Texture2D {
+            id,
+            width,
+            height,
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CubeFace {
+    PositiveX,
+    NegativeX,
+    PositiveY,
+    NegativeY,
+    PositiveZ,
+    NegativeZ,
+}
+
+impl From<CubeFace> for GLenum {
+    fn from(face: CubeFace) -> Self {
+        match face {
+            CubeFace::PositiveX => gl::TEXTURE_CUBE_MAP_POSITIVE_X,
+            CubeFace::NegativeX => gl::TEXTURE_CUBE_MAP_NEGATIVE_X,
+            CubeFace::PositiveY => gl::TEXTURE_CUBE_MAP_POSITIVE_Y,
+            CubeFace::NegativeY => gl::TEXTURE_CUBE_MAP_NEGATIVE_Y,
+            CubeFace::PositiveZ => gl::TEXTURE_CUBE_MAP_POSITIVE_Z,
+            CubeFace::NegativeZ => gl::TEXTURE_CUBE_MAP_NEGATIVE_Z,
+        }
+    }
+}
+
+impl CubeFace {
+    // The faces in the order OpenGL numbers them, which is also the order `from_files` expects.
+    pub fn all() -> [CubeFace; 6] {
+        [
+            CubeFace::PositiveX,
+            CubeFace::NegativeX,
+            CubeFace::PositiveY,
+            CubeFace::NegativeY,
+            CubeFace::PositiveZ,
+            CubeFace::NegativeZ,
+        ]
+    }
+}
+
+// Six square textures forming the inside of a cube, sampled with a direction vector instead of
+// texture coordinates.
+pub struct TextureCube {
+    id: Handle,
+    size: u32,
+}
+
+impl Drop for TextureCube {
+    fn drop(&mut self) {
+        unsafe { gl::DeleteTextures(1, &self.id); }
+    }
+}
+
+impl GlObject for TextureCube {
+    #[inline]
+    fn id(&self) -> Handle {
+        self.id
+    }
+}
+
+impl TextureCube {
+    // Cubemaps wrap to the edge by default. With `Repeat`, filtering near the edges of a face
+    // would blend in texels from its opposite edge, which shows up as seams.
+    pub fn builder() -> TextureBuilder<TextureCube> {
+        TextureBuilder::new().wrap(Wrap::ClampToEdge)
+    }
+
+    // The width (and height) of each face.
+    #[inline]
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    pub fn bind(&self, unit: u32) {
+        unsafe {
+            gl::ActiveTexture(gl::TEXTURE0 + unit);
+            gl::BindTexture(gl::TEXTURE_CUBE_MAP, self.id);
+        }
+    }
+}
+
+// Unlike 2D textures, cubemaps expect each face's top row first, which is how image files store
+// them. Leave `flip_vertically` off unless the faces were made for a flipped loader.
+impl TextureBuilder<TextureCube> {
+    // Loads one image per face, in `CubeFace::all()` order: right, left, top, bottom, front, back.
+    pub fn from_files<P: AsRef<Path>>(&self, paths: &[P; 6]) -> Result<TextureCube, TextureCreationError> {
+        let mut faces = Vec::with_capacity(6);
+
+        for path in paths {
+            faces.push(load_image(path.as_ref())?);
+        }
+
+        self.create_from_faces(&faces)
+    }
+
+    // Loads all six faces from a single image laid out as a cross, either horizontal (4 faces wide
+    // and 3 high) or vertical (3 wide and 4 high):
+    //
+    //         +Y                      +Y
+    //     -X  +Z  +X  -Z          -X  +Z  +X
+    //         -Y                      -Y
+    //                                 -Z
+    //
+    // In the vertical layout, -Z is upside down, as if the cross were folded into a cube.
+    pub fn from_cross_file<P: AsRef<Path>>(&self, path: P) -> Result<TextureCube, TextureCreationError> {
+        let image = load_image(path.as_ref())?;
+        let pixel_size = self.pixel_format(image.color)?.pixel_size();
+
+        let (width, height) = (image.width, image.height);
+
+        // The grid cell of each face, in `CubeFace::all()` order, and whether -Z is upside down.
+        let (size, cells, rotated) = if width * 3 == height * 4 {
+            (width / 4, [(2, 1), (0, 1), (1, 0), (1, 2), (1, 1), (3, 1)], false)
+        } else if width * 4 == height * 3 {
+            (width / 3, [(2, 1), (0, 1), (1, 0), (1, 2), (1, 1), (1, 3)], true)
+        } else {
+            return Err(TextureCreationError::InvalidCrossLayout { width, height });
+        };
+
+        let faces: Vec<_> = cells.iter().enumerate()
+            .map(|(i, &(column, row))| {
+                let upside_down = rotated && i == 5;
+                crop_face(&image, pixel_size, column * size, row * size, size, upside_down)
+            })
+            .collect();
+
+        self.create_from_faces(&faces)
+    }
+
+    fn create_from_faces(&self, faces: &[LoadedImage]) -> Result<TextureCube, TextureCreationError> {
+        let size = faces[0].width;
+        let color = faces[0].color;
+
+        for face in faces {
+            if face.width != face.height {
+                return Err(TextureCreationError::NonSquareCubeFace { width: face.width, height: face.height });
+            }
+
+            if face.width != size || face.color != color {
+                return Err(TextureCreationError::MismatchedCubeFaces);
+            }
+        }
+
+        let pixel_format = self.pixel_format(color)?;
+        let row_len = size as usize * pixel_format.pixel_size();
+
+        let mut id = 0;
+        unsafe {
+            gl::GenTextures(1, &mut id);
+            gl::BindTexture(gl::TEXTURE_CUBE_MAP, id);
+
+            for (face, &target) in faces.iter().zip(CubeFace::all().iter()) {
+                let data = self.rows(&face.pixels, row_len, size as usize);
+
+                with_unpack_alignment(row_len, || {
+                    gl::TexImage2D(target.into(), 0, pixel_format.internal_format as GLint,
+                        size as GLsizei, size as GLsizei, 0, pixel_format.format, pixel_format.ty,
+                        data.as_ptr() as *const GLvoid);
+                });
+            }
+        }
+
+        apply_swizzle(gl::TEXTURE_CUBE_MAP, &pixel_format);
+        self.parameters.apply(gl::TEXTURE_CUBE_MAP);
+
+        unsafe { gl::BindTexture(gl::TEXTURE_CUBE_MAP, 0); }
+
+        Ok(TextureCube {
+            id,
+            size,
+        })
+    }
+}
+
+// Types that raw texel data can be made of, along with how they are passed to OpenGL.
+pub trait TexelType: Copy {
+    fn gl_type() -> GLenum;
+}
+
+macro_rules! impl_texel_type {
+    ($($ty:ty => $gl_type:expr),*) => {
+        $(
+            impl TexelType for $ty {
+                fn gl_type() -> GLenum { $gl_type }
+            }
+        )*
+    }
+}
+
+impl_texel_type! {
+    i8  => gl::BYTE,
+    u8  => gl::UNSIGNED_BYTE,
+    i16 => gl::SHORT,
+    u16 => gl::UNSIGNED_SHORT,
+    i32 => gl::INT,
+    u32 => gl::UNSIGNED_INT,
+    f32 => gl::FLOAT
+}
+
+// A stack of 2D textures of the same size and format (the layers), sampled with a `sampler2DArray`
+// and a layer index. Unlike a texture atlas, filtering never bleeds from one layer into the next.
+pub struct Texture2DArray {
+    id: Handle,
+    width: u32,
+    height: u32,
+    layers: u32,
+
+    // From the builder, so that layers uploaded later are flipped like the ones it loaded.
+    flip_vertically: bool,
+}
+
+impl Drop for Texture2DArray {
+    fn drop(&mut self) {
+        unsafe { gl::DeleteTextures(1, &self.id); }
+    }
+}
+
+impl GlObject for Texture2DArray {
+    #[inline]
+    fn id(&self) -> Handle {
+        self.id
+    }
+}
+
+impl Texture2DArray {
+    pub fn builder() -> TextureBuilder<Texture2DArray> {
+        TextureBuilder::new()
+    }
+
+    #[inline]
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    #[inline]
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    #[inline]
+    pub fn layers(&self) -> u32 {
+        self.layers
+    }
+
+    pub fn bind(&self, unit: u32) {
+        unsafe {
+            gl::ActiveTexture(gl::TEXTURE0 + unit);
+            gl::BindTexture(gl::TEXTURE_2D_ARRAY, self.id);
+        }
+    }
+
+    // Replaces one layer with `data`, laid out as `format` says, eg. `gl::RGBA`. Only the base
+    // level is updated, so call `generate_mipmaps` afterwards when using mipmaps. Panics if `layer`
+    // is out of range.
+    pub fn upload_layer<T: TexelType>(&self, layer: u32, format: GLenum, data: &[T]) -> Result<(), TextureCreationError> {
+        assert!(layer < self.layers, "layer {} out of range: the array has {} layers", layer, self.layers);
+
+        self.layers_target().upload(layer, format, T::gl_type(), data)
+    }
+
+    pub fn upload_layer_image(&self, layer: u32, image: &DynamicImage) -> Result<(), TextureCreationError> {
+        assert!(layer < self.layers, "layer {} out of range: the array has {} layers", layer, self.layers);
+
+        check_layer_image(image, self.width, self.height)?;
+        let pixel_format = PixelFormat::from_color_type(image.color(), false)
+            .ok_or(TextureCreationError::UnsupportedColorType(image.color()))?;
+
+        self.layers_target().upload(layer, pixel_format.format, pixel_format.ty, &image.raw_pixels())
+    }
+
+    pub fn generate_mipmaps(&self) {
+        generate_mipmaps(gl::TEXTURE_2D_ARRAY, self.id);
+    }
+
+    fn layers_target(&self) -> LayersTarget {
+        LayersTarget {
+            target: gl::TEXTURE_2D_ARRAY,
+            id: self.id,
+            width: self.width,
+            height: self.height,
+            flip_vertically: self.flip_vertically,
+        }
+    }
+}
+
+impl TextureBuilder<Texture2DArray> {
+    // Allocates an array of `layers` uninitialized layers, to be filled with `upload_layer`. The
+    // internal format defaults to `gl::RGBA8`, or `gl::SRGB8_ALPHA8` with `srgb`.
+    //
+    // There's no data to generate mipmaps from yet, so call `generate_mipmaps` once the layers are
+    // uploaded, or turn mipmaps off.
+    pub fn with_size(&self, width: u32, height: u32, layers: u32) -> Texture2DArray {
+        let id = self.allocate_3d(gl::TEXTURE_2D_ARRAY, width, height, layers);
+
+        Texture2DArray {
+            id,
+            width,
+            height,
+            layers,
+            flip_vertically: self.flip_vertically,
+        }
+    }
+
+    // Loads one layer per file. All the images must have the same size and color type.
+    pub fn from_files<P: AsRef<Path>>(&self, paths: &[P]) -> Result<Texture2DArray, TextureCreationError> {
+        let (width, height, layers, id) = self.create_from_layer_files(gl::TEXTURE_2D_ARRAY, paths)?;
+
+        Ok(Texture2DArray {
+            id,
+            width,
+            height,
+            layers,
+            flip_vertically: self.flip_vertically,
+        })
+    }
+
+    // Creates an array from the data of all its layers, one after the other. With
+    // `flip_vertically`, each layer is flipped on its own.
+    pub fn from_raw<T: TexelType>(&self, width: u32, height: u32, layers: u32, format: GLenum, data: &[T])
+        -> Result<Texture2DArray, TextureCreationError>
+    {
+        let id = self.create_from_layer_data(gl::TEXTURE_2D_ARRAY, width, height, layers, format, data)?;
+
+        Ok(Texture2DArray {
+            id,
+            width,
+            height,
+            layers,
+            flip_vertically: self.flip_vertically,
+        })
+    }
+}
+
+// A volume of texels, sampled with a `sampler3D` and 3D texture coordinates. Unlike the layers of
+// an array texture, slices are filtered together.
+pub struct Texture3D {
+    id: Handle,
+    width: u32,
+    height: u32,
+    depth: u32,
+
+    // From the builder, so that slices uploaded later are flipped like the ones it loaded.
+    flip_vertically: bool,
+}
+
+impl Drop for Texture3D {
+    fn drop(&mut self) {
+        unsafe { gl::DeleteTextures(1, &self.id); }
+    }
+}
+
+impl GlObject for Texture3D {
+    #[inline]
+    fn id(&self) -> Handle {
+        self.id
+    }
+}
+
+impl Texture3D {
+    pub fn builder() -> TextureBuilder<Texture3D> {
+        TextureBuilder::new()
+    }
+
+    #[inline]
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    #[inline]
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    #[inline]
+    pub fn depth(&self) -> u32 {
+        self.depth
+    }
+
+    pub fn bind(&self, unit: u32) {
+        unsafe {
+            gl::ActiveTexture(gl::TEXTURE0 + unit);
+            gl::BindTexture(gl::TEXTURE_3D, self.id);
+        }
+    }
+
+    // Replaces one slice with `data`, laid out as `format` says, eg. `gl::RED`. Only the base level
+    // is updated, so call `generate_mipmaps` afterwards when using mipmaps. Panics if `slice` is out
+    // of range.
+    pub fn upload_slice<T: TexelType>(&self, slice: u32, format: GLenum, data: &[T]) -> Result<(), TextureCreationError> {
+        assert!(slice < self.depth, "slice {} out of range: the texture has {} slices", slice, self.depth);
+
+        self.layers_target().upload(slice, format, T::gl_type(), data)
+    }
+
+    pub fn upload_slice_image(&self, slice: u32, image: &DynamicImage) -> Result<(), TextureCreationError> {
+        assert!(slice < self.depth, "slice {} out of range: the texture has {} slices", slice, self.depth);
+
+        check_layer_image(image, self.width, self.height)?;
+        let pixel_format = PixelFormat::from_color_type(image.color(), false)
+            .ok_or(TextureCreationError::UnsupportedColorType(image.color()))?;
+
+        self.layers_target().upload(slice, pixel_format.format, pixel_format.ty, &image.raw_pixels())
+    }
+
+    pub fn generate_mipmaps(&self) {
+        generate_mipmaps(gl::TEXTURE_3D, self.id);
+    }
+
+    fn layers_target(&self) -> LayersTarget {
+        LayersTarget {
+            target: gl::TEXTURE_3D,
+            id: self.id,
+            width: self.width,
+            height: self.height,
+            flip_vertically: self.flip_vertically,
+        }
+    }
+}
+
+impl TextureBuilder<Texture3D> {
+    // Allocates `depth` uninitialized slices, to be filled with `upload_slice`. The internal format
+    // defaults to `gl::RGBA8`, or `gl::SRGB8_ALPHA8` with `srgb`.
+    //
+    // There's no data to generate mipmaps from yet, so call `generate_mipmaps` once the slices are
+    // uploaded, or turn mipmaps off.
+    pub fn with_size(&self, width: u32, height: u32, depth: u32) -> Texture3D {
+        let id = self.allocate_3d(gl::TEXTURE_3D, width, height, depth);
+
+        Texture3D {
+            id,
+            width,
+            height,
+            depth,
+            flip_vertically: self.flip_vertically,
+        }
+    }
+
+    // Loads one slice per file. All the images must have the same size and color type.
+    pub fn from_files<P: AsRef<Path>>(&self, paths: &[P]) -> Result<Texture3D, TextureCreationError> {
+        let (width, height, depth, id) = self.create_from_layer_files(gl::TEXTURE_3D, paths)?;
+
+        Ok(Texture3D {
+            id,
+            width,
+            height,
+            depth,
+            flip_vertically: self.flip_vertically,
+        })
+    }
+
+    // Creates a texture from the data of all its slices, one after the other, eg. `f32` densities
+    // with `gl::RED` and `internal_format(gl::R32F)`.
+    pub fn from_raw<T: TexelType>(&self, width: u32, height: u32, depth: u32, format: GLenum, data: &[T])
+        -> Result<Texture3D, TextureCreationError>
+    {
+        let id = self.create_from_layer_data(gl::TEXTURE_3D, width, height, depth, format, data)?;
+
+        Ok(Texture3D {
+            id,
+            width,
+            height,
+            depth,
+            flip_vertically: self.flip_vertically,
+        })
+    }
+}
+
+// Array and 3D textures are both created as a stack of 2D images, and only differ in how they are
+// sampled.
+impl<T> TextureBuilder<T> {
+    // Allocates a texture of `depth` uninitialized layers. The internal format defaults to
+    // `gl::RGBA8`, or `gl::SRGB8_ALPHA8` with `srgb`.
+    fn allocate_3d(&self, target: GLenum, width: u32, height: u32, depth: u32) -> GLuint {
+        let default_format = if self.srgb { gl::SRGB8_ALPHA8 } else { gl::RGBA8 };
+        let internal_format = self.internal_format.unwrap_or(default_format);
+        let (format, ty) = upload_format(internal_format);
+
+        let mut id = 0;
+        unsafe {
+            gl::GenTextures(1, &mut id);
+            gl::BindTexture(target, id);
+
+            gl::TexImage3D(target, 0, internal_format as GLint, width as GLsizei, height as GLsizei,
+                depth as GLsizei, 0, format, ty, ptr::null());
+        }
+
+        self.parameters.apply_sampling(target);
+
+        unsafe { gl::BindTexture(target, 0); }
+
+        id
+    }
+
+    fn create_from_layer_files<P: AsRef<Path>>(&self, target: GLenum, paths: &[P])
+        -> Result<(u32, u32, u32, GLuint), TextureCreationError>
+    {
+        assert!(!paths.is_empty(), "a layered texture needs at least one layer");
+
+        let mut images = Vec::with_capacity(paths.len());
+
+        for path in paths {
+            images.push(load_image(path.as_ref())?);
+        }
+
+        let (width, height, color) = (images[0].width, images[0].height, images[0].color);
+
+        if images.iter().any(|image| image.width != width || image.height != height || image.color != color) {
+            return Err(TextureCreationError::MismatchedLayers);
+        }
+
+        let pixel_format = self.pixel_format(color)?;
+        let row_len = width as usize * pixel_format.pixel_size();
+        let depth = images.len() as u32;
+
+        let mut id = 0;
+        unsafe {
+            gl::GenTextures(1, &mut id);
+            gl::BindTexture(target, id);
+
+            gl::TexImage3D(target, 0, pixel_format.internal_format as GLint, width as GLsizei,
+                height as GLsizei, depth as GLsizei, 0, pixel_format.format, pixel_format.ty, ptr::null());
+
+            for (layer, image) in images.iter().enumerate() {
+                let data = self.rows(&image.pixels, row_len, height as usize);
+
+                with_unpack_alignment(row_len, || {
+                    gl::TexSubImage3D(target, 0, 0, 0, layer as GLint, width as GLsizei, height as GLsizei, 1,
+                        pixel_format.format, pixel_format.ty, data.as_ptr() as *const GLvoid);
+                });
+            }
+        }
+
+        apply_swizzle(target, &pixel_format);
+        self.parameters.apply(target);
+
+        unsafe { gl::BindTexture(target, 0); }
+
+        Ok((width, height, depth, id))
+    }
+
+    fn create_from_layer_data<D: TexelType>(&self, target: GLenum, width: u32, height: u32, depth: u32, format: GLenum,
+        data: &[D]) -> Result<GLuint, TextureCreationError>
+    {
+        let ty = D::gl_type();
+        let pixel_size = components(format) * type_size(ty);
+        let expected = width as usize * height as usize * depth as usize * pixel_size;
+        let actual = mem::size_of_val(data);
+
+        if actual != expected {
+            return Err(TextureCreationError::InvalidDataLength { expected, actual });
+        }
+
+        let internal_format = self.internal_format.unwrap_or(format);
+        let row_len = width as usize * pixel_size;
+        let data = self.rows(data, width as usize * components(format), height as usize);
+
+        let mut id = 0;
+        unsafe {
+            gl::GenTextures(1, &mut id);
+            gl::BindTexture(target, id);
+
+            with_unpack_alignment(row_len, || {
+                gl::TexImage3D(target, 0, internal_format as GLint, width as GLsizei, height as GLsizei,
+                    depth as GLsizei, 0, format, ty, data.as_ptr() as *const GLvoid);
+            });
+        }
+
+        self.parameters.apply(target);
+
+        unsafe { gl::BindTexture(target, 0); }
+
+        Ok(id)
+    }
+}
+
+fn rows<'a, D: Clone>(flip_vertically: bool, data: &'a [D], row_len: usize, height: usize) -> Cow<'a, [D]> {
+    if flip_vertically && row_len > 0 && height > 0 {
+        Cow::Owned(data.chunks(row_len * height)
+            .flat_map(|image| image.chunks(row_len).rev())
+            .flat_map(|row| row.iter().cloned())
+            .collect())
+    } else {
+        Cow::Borrowed(data)
+    }
+}
+
+fn check_layer_image(image: &DynamicImage, width: u32, height: u32) -> Result<(), TextureCreationError> {
+    if image.dimensions() != (width, height) {
+        return Err(TextureCreationError::MismatchedLayers);
+    }
+
+    Ok(())
+}
+
+// An array or 3D texture that layers are uploaded to after it was created.
+struct LayersTarget {
+    target: GLenum,
+    id: GLuint,
+    width: u32,
+    height: u32,
+    flip_vertically: bool,
+}
+
+impl LayersTarget {
+    // Uploads one layer, flipped if `flip_vertically` is set.
+    fn upload<D: Copy>(&self, layer: u32, format: GLenum, ty: GLenum, data: &[D]) -> Result<(), TextureCreationError> {
+        let row_len = self.width as usize * components(format) * type_size(ty);
+        let expected = row_len * self.height as usize;
+        let actual = mem::size_of_val(data);
+
+        if actual != expected {
+            return Err(TextureCreationError::InvalidDataLength { expected, actual });
+        }
+
+        let data = rows(self.flip_vertically, data, row_len / mem::size_of::<D>(), self.height as usize);
+
+        unsafe {
+            gl::BindTexture(self.target, self.id);
+
+            with_unpack_alignment(row_len, || {
+                gl::TexSubImage3D(self.target, 0, 0, 0, layer as GLint, self.width as GLsizei,
+                    self.height as GLsizei, 1, format, ty, data.as_ptr() as *const GLvoid);
+            });
+
+            gl::BindTexture(self.target, 0);
+        }
+
+        Ok(())
+    }
+}
+
+fn generate_mipmaps(target: GLenum, id: GLuint) {
+    unsafe {
+        gl::BindTexture(target, id);
+        gl::GenerateMipmap(target);
+        gl::BindTexture(target, 0);
+    }
+}
+
+// Decoded pixels, in their original color type.
+struct LoadedImage {
+    width: u32,
+    height: u32,
+    color: ColorType,
+    pixels: Vec<u8>,
+}
+
+// `image` strips 16-bit PNGs down to 8 bits per sample, so we decode PNGs with `png` ourselves to
+// keep all 16 bits.
+fn load_image(path: &Path) -> Result<LoadedImage, TextureCreationError> {
+    let is_png = path.extension().map_or(false, |ext| ext.eq_ignore_ascii_case("png"));
+
+    if !is_png {
+        let image = image::open(path).map_err(TextureCreationError::ImageError)?;
+        let (width, height) = image.dimensions();
+
+        return Ok(LoadedImage {
+            width,
+            height,
+            color: image.color(),
+            pixels: image.raw_pixels(),
+        });
+    }
+
+    let file = fs::File::open(path)
+        .map_err(|e| TextureCreationError::ImageError(image::ImageError::IoError(e)))?;
+
+    decode_png(io::BufReader::new(file)).map_err(TextureCreationError::ImageError)
+}
+
+fn decode_png<R: io::Read>(reader: R) -> image::ImageResult<LoadedImage> {
+    // Only expand palettes, transparency and bit depths below 8; 16-bit samples are kept as is.
+    let mut decoder = png::Decoder::new(reader);
+    decoder.set(png::TRANSFORM_EXPAND);
+
+    let (info, mut reader) = decoder.read_info().map_err(png_error)?;
+    let mut pixels = vec![0; info.buffer_size()];
+    reader.next_frame(&mut pixels).map_err(png_error)?;
+
+    let (color_type, bit_depth) = reader.output_color_type();
+    let bits = bit_depth as u8;
+
+    let color = match color_type {
+        png::ColorType::Grayscale      => ColorType::Gray(bits),
+        png::ColorType::RGB            => ColorType::RGB(bits),
+        png::ColorType::Indexed        => ColorType::Palette(bits),
+        png::ColorType::GrayscaleAlpha => ColorType::GrayA(bits),
+        png::ColorType::RGBA           => ColorType::RGBA(bits),
+    };
+
+    // PNG stores 16-bit samples in big-endian order, but they are uploaded as `UNSIGNED_SHORT`,
+    // which OpenGL reads in native byte order.
+    if bits == 16 {
+        for sample in pixels.chunks_mut(mem::size_of::<u16>()) {
+            let value = u16::from_be_bytes([sample[0], sample[1]]);
+            sample.copy_from_slice(&value.to_ne_bytes());
+        }
+    }
+
+    Ok(LoadedImage {
+        width: info.width,
+        height: info.height,
+        color,
+        pixels,
+    })
+}
+
+fn png_error(error: png::DecodingError) -> image::ImageError {
+    match error {
+        png::DecodingError::IoError(error) => image::ImageError::IoError(error),
+        error                              => image::ImageError::FormatError(error.to_string()),
+    }
+}
+
+// Copies the `size` by `size` square at (`x`, `y`) out of `image`, optionally rotated by 180
+// degrees.
+fn crop_face(image: &LoadedImage, pixel_size: usize, x: u32, y: u32, size: u32, rotate: bool) -> LoadedImage {
+    let row_len = image.width as usize * pixel_size;
+    let face_row_len = size as usize * pixel_size;
+
+    let mut rows: Vec<&[u8]> = (y..y + size)
+        .map(|row| {
+            let start = row as usize * row_len + x as usize * pixel_size;
+            &image.pixels[start..start + face_row_len]
+        })
+        .collect();
+
+    if rotate {
+        rows.reverse();
+    }
+
+    let mut pixels = Vec::with_capacity(face_row_len * size as usize);
+
+    for row in rows {
+        if rotate {
+            for pixel in row.chunks(pixel_size).rev() {
+                pixels.extend_from_slice(pixel);
+            }
+        } else {
+            pixels.extend_from_slice(row);
+        }
+    }
+
+    LoadedImage {
+        width: size,
+        height: size,
+        color: image.color,
+        pixels,
+    }
+}
+
+// OpenGL expects each row of pixel data to start at a multiple of `GL_UNPACK_ALIGNMENT` bytes (4 by
+// default). Our rows are tightly packed, so we use the largest alignment their length allows, eg. 1
+// for an RGB image with an odd width.
+unsafe fn with_unpack_alignment<F: FnOnce()>(row_len: usize, upload: F) {
+    let alignment = [8, 4, 2, 1].iter().cloned().find(|&a| row_len.is_multiple_of(a)).unwrap();
+
+    gl::PixelStorei(gl::UNPACK_ALIGNMENT, alignment as GLint);
+    upload();
+    gl::PixelStorei(gl::UNPACK_ALIGNMENT, 4);
+}
+
+fn apply_swizzle(target: GLenum, pixel_format: &PixelFormat) {
+    if let Some(swizzle) = pixel_format.swizzle() {
+        unsafe { gl::TexParameteriv(target, gl::TEXTURE_SWIZZLE_RGBA, swizzle.as_ptr()); }
+    }
+}
+
+// A pixel format and type that `internal_format` accepts, to allocate storage without data.
+// OpenGL checks that they are compatible even then, eg. integer formats need `*_INTEGER` data.
+fn upload_format(internal_format: GLenum) -> (GLenum, GLenum) {
+    match internal_format {
+        gl::R8 | gl::R16 | gl::R16F | gl::R32F                                  => (gl::RED, gl::FLOAT),
+        gl::RG8 | gl::RG16 | gl::RG16F | gl::RG32F                              => (gl::RG, gl::FLOAT),
+        gl::RGB8 | gl::SRGB8 | gl::RGB16 | gl::RGB16F | gl::RGB32F              => (gl::RGB, gl::FLOAT),
+        gl::R8UI | gl::R16UI | gl::R32UI | gl::R8I | gl::R16I | gl::R32I        => (gl::RED_INTEGER, gl::INT),
+        gl::RG8UI | gl::RG16UI | gl::RG32UI | gl::RG8I | gl::RG16I | gl::RG32I  => (gl::RG_INTEGER, gl::INT),
+        gl::RGBA8UI | gl::RGBA16UI | gl::RGBA32UI |
+        gl::RGBA8I | gl::RGBA16I | gl::RGBA32I                                  => (gl::RGBA_INTEGER, gl::INT),
+        gl::DEPTH_COMPONENT16 | gl::DEPTH_COMPONENT24 | gl::DEPTH_COMPONENT32F  => (gl::DEPTH_COMPONENT, gl::FLOAT),
+        gl::DEPTH24_STENCIL8                                                    => (gl::DEPTH_STENCIL, gl::UNSIGNED_INT_24_8),
+        gl::DEPTH32F_STENCIL8                                                   => (gl::DEPTH_STENCIL, gl::FLOAT_32_UNSIGNED_INT_24_8_REV),
+        _                                                                       => (gl::RGBA, gl::FLOAT),
+    }
+}
+
+fn components(format: GLenum) -> usize {
+    match format {
+        gl::RED | gl::RED_INTEGER | gl::DEPTH_COMPONENT | gl::STENCIL_INDEX => 1,
+        gl::DEPTH_STENCIL                                                  => 1,
+        gl::RG | gl::RG_INTEGER                                            => 2,
+        gl::RGB | gl::BGR | gl::RGB_INTEGER | gl::BGR_INTEGER              => 3,
+        _                                                                  => 4,
+    }
+}
+
+fn type_size(ty: GLenum) -> usize {
+    match ty {
+        gl::UNSIGNED_BYTE | gl::BYTE                   => 1,
+        gl::UNSIGNED_SHORT | gl::SHORT | gl::HALF_FLOAT => 2,
+        _                                              => 4,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_16_bit_png_in_native_byte_order() {
+        // A 2x1 grayscale image with 16 bits per sample, holding 0x0102 and 0xabcd.
+        let png: &[u8] = include_bytes!("../tests/textures/gray16.png");
+        let image = decode_png(png).unwrap();
+
+        assert_eq!((image.width, image.height), (2, 1));
+        assert_eq!(image.color, ColorType::Gray(16));
+
+        let samples: Vec<u16> = image.pixels.chunks(2)
+            .map(|sample| u16::from_ne_bytes([sample[0], sample[1]]))
+            .collect();
+
+        assert_eq!(samples, [0x0102, 0xabcd]);
+    }
+}
@@ -0,0 +1,238 @@
+use std::time::{Duration, Instant};
+
+// How much the newest frame counts in `Clock::fps`. Lower values smooth out more of the jitter,
+// but react more slowly to real changes in frame rate.
+const FPS_SMOOTHING: f32 = 0.1;
+
+// Measures frame times. Call `tick` once per frame:
+//
+//     let mut clock = Clock::new();
+//
+//     loop {
+//         let dt = clock.tick();
+//         ...
+//     }
+pub struct Clock {
+    start: Instant,
+    last_tick: Instant,
+    delta: f32,
+    frames: u64,
+    average_delta: f32,
+}
+
+impl Clock {
+    pub fn new() -> Clock {
+        let now = Instant::now();
+
+        Clock {
+            start: now,
+            last_tick: now,
+            delta: 0.0,
+            frames: 0,
+            average_delta: 0.0,
+        }
+    }
+
+    // Starts a new frame, and returns the time since the previous one, or since the clock was
+    // created for the first frame, in seconds.
+    pub fn tick(&mut self) -> f32 {
+        let now = Instant::now();
+
+        self.delta = seconds(now.duration_since(self.last_tick)) as f32;
+        self.last_tick = now;
+
+        self.average_delta = if self.frames == 0 {
+            self.delta
+        } else {
+            self.average_delta + (self.delta - self.average_delta) * FPS_SMOOTHING
+        };
+
+        self.frames += 1;
+
+        self.delta
+    }
+
+    // Seconds since the clock was created.
+    pub fn elapsed(&self) -> f64 {
+        seconds(self.start.elapsed())
+    }
+
+    // Seconds between the last two ticks.
+    pub fn delta(&self) -> f32 {
+        self.delta
+    }
+
+    // The number of ticks so far.
+    pub fn frames(&self) -> u64 {
+        self.frames
+    }
+
+    // Frames per second, averaged over the last few dozen frames so the number is readable. Zero
+    // before the first tick.
+    pub fn fps(&self) -> f32 {
+        if self.average_delta > 0.0 {
+            1.0 / self.average_delta
+        } else {
+            0.0
+        }
+    }
+}
+
+impl Default for Clock {
+    fn default() -> Clock {
+        Clock::new()
+    }
+}
+
+// Splits variable frame times into fixed steps, so a simulation gives the same results whatever
+// the frame rate:
+//
+//     timestep.accumulate(dt);
+//
+//     while timestep.step() {
+//         world.update(timestep.dt());
+//     }
+//
+//     world.render(timestep.alpha());
+//
+// Time that doesn't add up to a whole step is carried over to the next frame. `alpha` tells how far
+// into the next step the current time is, so rendering can interpolate between the last two
+// simulated states.
+#[derive(Clone, Debug)]
+pub struct FixedTimestep {
+    dt: f64,
+    max_frame_time: f64,
+    accumulator: f64,
+}
+
+impl FixedTimestep {
+    // Steps `dt` seconds at a time, eg. `1.0 / 60.0`. A step that isn't positive would never consume
+    // the accumulated time, so `step` would keep returning true.
+    pub fn new(dt: f32) -> FixedTimestep {
+        assert!(dt > 0.0, "the fixed timestep must be positive, got {}", dt);
+
+        FixedTimestep {
+            dt: dt as f64,
+            max_frame_time: 0.25,
+            accumulator: 0.0,
+        }
+    }
+
+    // Limits how much time a single frame can add, 0.25s by default. After a long stall, eg. while
+    // the window is dragged or a breakpoint is hit, the simulation slows down instead of running
+    // many steps to catch up, which could make the next frame slow too, and so on.
+    pub fn with_max_frame_time(mut self, seconds: f32) -> FixedTimestep {
+        self.max_frame_time = seconds as f64;
+        self
+    }
+
+    // Adds the time since the previous frame.
+    pub fn accumulate(&mut self, frame_time: f32) {
+        self.accumulator += (frame_time as f64).min(self.max_frame_time);
+    }
+
+    // Consumes one step of the accumulated time. Returns false when there's less than a step left.
+    pub fn step(&mut self) -> bool {
+        if self.accumulator >= self.dt {
+            self.accumulator -= self.dt;
+            true
+        } else {
+            false
+        }
+    }
+
+    // The length of a step in seconds.
+    pub fn dt(&self) -> f32 {
+        self.dt as f32
+    }
+
+    // How far the leftover time goes into the next step, between 0 and 1.
+    pub fn alpha(&self) -> f32 {
+        (self.accumulator / self.dt) as f32
+    }
+}
+
+// A time that can be paused and slowed down or sped up, to look at animations more closely. It's
+// driven by frame times, eg. from `Clock::tick`:
+//
+//     let dt = time.advance(clock.tick());
+//     scene.draw(time.seconds());
+#[derive(Clone, Debug)]
+pub struct TimeSource {
+    seconds: f64,
+    scale: f32,
+    paused: bool,
+    // Advance by one frame even though paused.
+    single_step: bool,
+}
+
+impl TimeSource {
+    pub fn new() -> TimeSource {
+        TimeSource {
+            seconds: 0.0,
+            scale: 1.0,
+            paused: false,
+            single_step: false,
+        }
+    }
+
+    // Moves time forward by `frame_time` seconds times the scale, and returns by how much. Time
+    // doesn't move while paused.
+    pub fn advance(&mut self, frame_time: f32) -> f32 {
+        if self.paused && !self.single_step {
+            return 0.0;
+        }
+
+        self.single_step = false;
+
+        let dt = frame_time * self.scale;
+        self.seconds += dt as f64;
+
+        dt
+    }
+
+    pub fn seconds(&self) -> f64 {
+        self.seconds
+    }
+
+    pub fn scale(&self) -> f32 {
+        self.scale
+    }
+
+    // Sets how fast time goes, eg. 0.1 for slow motion. Negative scales run time backwards.
+    pub fn set_scale(&mut self, scale: f32) {
+        self.scale = scale;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
+
+    pub fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+    }
+
+    // While paused, lets the next `advance` move time forward, to go through an animation one
+    // frame at a time.
+    pub fn step_frame(&mut self) {
+        self.single_step = true;
+    }
+}
+
+impl Default for TimeSource {
+    fn default() -> TimeSource {
+        TimeSource::new()
+    }
+}
+
+fn seconds(duration: Duration) -> f64 {
+    duration.as_secs() as f64 + duration.subsec_nanos() as f64 * 1e-9
+}
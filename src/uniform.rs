@@ -0,0 +1,123 @@
+use gl;
+use gl::types::*;
+
+// Types that can be uploaded to a uniform variable with `Program::set_uniform`.
+//
+// Matrices are expected in column-major order, which is what GLSL uses, so each inner array is a
+// column of the matrix.
+pub trait Uniform {
+    /// Uploads the value to the uniform variable at `location`.
+    ///
+    /// # Safety
+    ///
+    /// The program must be bound with `Program::activate`, and `location` must be a uniform location
+    /// that belongs to it, for a variable whose type matches `Self`.
+    unsafe fn set(&self, location: GLint);
+}
+
+// A sampler uniform holds the index of a texture unit, eg. `Sampler(1)` for `gl::TEXTURE1`.
+#[derive(Clone, Copy, Debug)]
+pub struct Sampler(pub u32);
+
+impl Uniform for f32 {
+    unsafe fn set(&self, location: GLint) {
+        gl::Uniform1f(location, *self);
+    }
+}
+
+impl Uniform for [f32; 2] {
+    unsafe fn set(&self, location: GLint) {
+        gl::Uniform2f(location, self[0], self[1]);
+    }
+}
+
+impl Uniform for [f32; 3] {
+    unsafe fn set(&self, location: GLint) {
+        gl::Uniform3f(location, self[0], self[1], self[2]);
+    }
+}
+
+impl Uniform for [f32; 4] {
+    unsafe fn set(&self, location: GLint) {
+        gl::Uniform4f(location, self[0], self[1], self[2], self[3]);
+    }
+}
+
+impl Uniform for i32 {
+    unsafe fn set(&self, location: GLint) {
+        gl::Uniform1i(location, *self);
+    }
+}
+
+impl Uniform for [i32; 2] {
+    unsafe fn set(&self, location: GLint) {
+        gl::Uniform2i(location, self[0], self[1]);
+    }
+}
+
+impl Uniform for [i32; 3] {
+    unsafe fn set(&self, location: GLint) {
+        gl::Uniform3i(location, self[0], self[1], self[2]);
+    }
+}
+
+impl Uniform for [i32; 4] {
+    unsafe fn set(&self, location: GLint) {
+        gl::Uniform4i(location, self[0], self[1], self[2], self[3]);
+    }
+}
+
+impl Uniform for u32 {
+    unsafe fn set(&self, location: GLint) {
+        gl::Uniform1ui(location, *self);
+    }
+}
+
+impl Uniform for [u32; 2] {
+    unsafe fn set(&self, location: GLint) {
+        gl::Uniform2ui(location, self[0], self[1]);
+    }
+}
+
+impl Uniform for [u32; 3] {
+    unsafe fn set(&self, location: GLint) {
+        gl::Uniform3ui(location, self[0], self[1], self[2]);
+    }
+}
+
+impl Uniform for [u32; 4] {
+    unsafe fn set(&self, location: GLint) {
+        gl::Uniform4ui(location, self[0], self[1], self[2], self[3]);
+    }
+}
+
+// GLSL booleans are set with the integer functions.
+impl Uniform for bool {
+    unsafe fn set(&self, location: GLint) {
+        gl::Uniform1i(location, *self as GLint);
+    }
+}
+
+impl Uniform for [[f32; 2]; 2] {
+    unsafe fn set(&self, location: GLint) {
+        gl::UniformMatrix2fv(location, 1, gl::FALSE, self.as_ptr() as *const GLfloat);
+    }
+}
+
+impl Uniform for [[f32; 3]; 3] {
+    unsafe fn set(&self, location: GLint) {
+        gl::UniformMatrix3fv(location, 1, gl::FALSE, self.as_ptr() as *const GLfloat);
+    }
+}
+
+impl Uniform for [[f32; 4]; 4] {
+    unsafe fn set(&self, location: GLint) {
+        gl::UniformMatrix4fv(location, 1, gl::FALSE, self.as_ptr() as *const GLfloat);
+    }
+}
+
+impl Uniform for Sampler {
+    unsafe fn set(&self, location: GLint) {
+        gl::Uniform1i(location, self.0 as GLint);
+    }
+}
@@ -0,0 +1,338 @@
+use gl;
+use gl::types::*;
+
+use std::mem;
+
+use buffer::{Buffer, BufferTarget};
+use gl_object::{GlObject, Handle};
+use program::Program;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AttributeType {
+    Byte,
+    UnsignedByte,
+    Short,
+    UnsignedShort,
+    Int,
+    UnsignedInt,
+    HalfFloat,
+    Float,
+    Double,
+}
+
+impl From<AttributeType> for GLenum {
+    fn from(ty: AttributeType) -> Self {
+        match ty {
+            AttributeType::Byte          => gl::BYTE,
+            AttributeType::UnsignedByte  => gl::UNSIGNED_BYTE,
+            AttributeType::Short         => gl::SHORT,
+            AttributeType::UnsignedShort => gl::UNSIGNED_SHORT,
+            AttributeType::Int           => gl::INT,
+            AttributeType::UnsignedInt   => gl::UNSIGNED_INT,
+            AttributeType::HalfFloat     => gl::HALF_FLOAT,
+            AttributeType::Float         => gl::FLOAT,
+            AttributeType::Double        => gl::DOUBLE,
+        }
+    }
+}
+
+impl AttributeType {
+    pub fn size(&self) -> usize {
+        match *self {
+            AttributeType::Byte          => mem::size_of::<GLbyte>(),
+            AttributeType::UnsignedByte  => mem::size_of::<GLubyte>(),
+            AttributeType::Short         => mem::size_of::<GLshort>(),
+            AttributeType::UnsignedShort => mem::size_of::<GLushort>(),
+            AttributeType::Int           => mem::size_of::<GLint>(),
+            AttributeType::UnsignedInt   => mem::size_of::<GLuint>(),
+            AttributeType::HalfFloat     => mem::size_of::<GLhalf>(),
+            AttributeType::Float         => mem::size_of::<GLfloat>(),
+            AttributeType::Double        => mem::size_of::<GLdouble>(),
+        }
+    }
+
+    fn is_integer(&self) -> bool {
+        match *self {
+            AttributeType::HalfFloat | AttributeType::Float | AttributeType::Double => false,
+            _ => true,
+        }
+    }
+}
+
+// Describes one vertex attribute: which shader input it feeds (`location`), and how many values of
+// which type make up each element, eg. 3 floats for a `vec3`.
+//
+// Integer data is converted to floats for the shader, optionally normalized to [0, 1] (or [-1, 1]
+// for signed types). Use `VertexAttribute::integer` for `int`/`ivec` shader inputs instead.
+// `AttributeType::Double` data feeds `double`/`dvec` inputs as is, which needs OpenGL 4.1.
+//
+// Attributes can also be `named` after their shader input, so that `VertexLayout::resolve` can look
+// up the location in the linked program instead.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VertexAttribute {
+    pub location: GLuint,
+    pub components: GLint,
+    pub ty: AttributeType,
+    pub normalized: bool,
+    pub integer: bool,
+    pub name: Option<&'static str>,
+}
+
+impl VertexAttribute {
+    pub fn new(location: GLuint, components: GLint, ty: AttributeType) -> VertexAttribute {
+        VertexAttribute {
+            location,
+            components,
+            ty,
+            normalized: false,
+            integer: false,
+            name: None,
+        }
+    }
+
+    pub fn float(location: GLuint, components: GLint) -> VertexAttribute {
+        VertexAttribute::new(location, components, AttributeType::Float)
+    }
+
+    pub fn integer(location: GLuint, components: GLint, ty: AttributeType) -> VertexAttribute {
+        assert!(ty.is_integer(), "{:?} is not an integer type", ty);
+
+        VertexAttribute {
+            integer: true,
+            ..VertexAttribute::new(location, components, ty)
+        }
+    }
+
+    pub fn normalized(mut self) -> VertexAttribute {
+        self.normalized = true;
+        self
+    }
+
+    pub fn named(mut self, name: &'static str) -> VertexAttribute {
+        self.name = Some(name);
+        self
+    }
+
+    pub fn size(&self) -> usize {
+        self.components as usize * self.ty.size()
+    }
+}
+
+// The layout of the vertices in one buffer. Attributes added with `with_attribute` are packed one
+// after the other, so the offsets and the stride are computed for us. When the vertices are Rust
+// structs, `#[derive(Vertex)]` builds the layout from the struct's actual field offsets instead.
+//
+// For non-interleaved data, use one layout (and one buffer) per attribute.
+#[derive(Clone, Debug)]
+pub struct VertexLayout {
+    attributes: Vec<(VertexAttribute, usize)>,
+    stride: usize,
+}
+
+impl VertexLayout {
+    pub fn new() -> VertexLayout {
+        VertexLayout {
+            attributes: Vec::new(),
+            stride: 0,
+        }
+    }
+
+    pub fn with_attribute(mut self, attribute: VertexAttribute) -> VertexLayout {
+        self.attributes.push((attribute, self.stride));
+        self.stride += attribute.size();
+        self
+    }
+
+    // Adds an attribute at an explicit byte offset, eg. to account for padding. The stride grows to
+    // cover the attribute if needed.
+    pub fn with_attribute_at(mut self, attribute: VertexAttribute, offset: usize) -> VertexLayout {
+        self.attributes.push((attribute, offset));
+        self.stride = self.stride.max(offset + attribute.size());
+        self
+    }
+
+    pub fn with_stride(mut self, stride: usize) -> VertexLayout {
+        self.stride = stride;
+        self
+    }
+
+    // The attributes, along with their byte offset from the start of the vertex.
+    pub fn attributes(&self) -> &[(VertexAttribute, usize)] {
+        &self.attributes
+    }
+
+    // The byte distance between consecutive vertices.
+    pub fn stride(&self) -> usize {
+        self.stride
+    }
+
+    // Replaces the location of each named attribute with the one `program` gives its shader input.
+    // Named attributes that aren't active in `program` are left out, since nothing would read them.
+    pub fn resolve(&self, program: &Program) -> VertexLayout {
+        let attributes = self.attributes.iter()
+            .filter_map(|&(attribute, offset)| match attribute.name {
+                Some(name) => program.attribute_location(name)
+                    .map(|location| (VertexAttribute { location, ..attribute }, offset)),
+                None => Some((attribute, offset)),
+            })
+            .collect();
+
+        VertexLayout {
+            attributes,
+            stride: self.stride,
+        }
+    }
+}
+
+impl Default for VertexLayout {
+    fn default() -> VertexLayout {
+        VertexLayout::new()
+    }
+}
+
+// Vertex types that know their own layout. Implement this with `#[derive(Vertex)]` from the
+// `learn_opengl_derive` crate rather than by hand.
+pub trait Vertex: Copy {
+    fn layout() -> VertexLayout;
+}
+
+// Field types that can be used as vertex attributes, along with how they are passed to OpenGL.
+pub trait AttributeFormat {
+    fn components() -> GLint;
+    fn attribute_type() -> AttributeType;
+}
+
+macro_rules! impl_attribute_format {
+    ($($ty:ty => $attribute_type:ident),*) => {
+        $(
+            impl AttributeFormat for $ty {
+                fn components() -> GLint { 1 }
+                fn attribute_type() -> AttributeType { AttributeType::$attribute_type }
+            }
+
+            impl AttributeFormat for [$ty; 1] {
+                fn components() -> GLint { 1 }
+                fn attribute_type() -> AttributeType { AttributeType::$attribute_type }
+            }
+
+            impl AttributeFormat for [$ty; 2] {
+                fn components() -> GLint { 2 }
+                fn attribute_type() -> AttributeType { AttributeType::$attribute_type }
+            }
+
+            impl AttributeFormat for [$ty; 3] {
+                fn components() -> GLint { 3 }
+                fn attribute_type() -> AttributeType { AttributeType::$attribute_type }
+            }
+
+            impl AttributeFormat for [$ty; 4] {
+                fn components() -> GLint { 4 }
+                fn attribute_type() -> AttributeType { AttributeType::$attribute_type }
+            }
+        )*
+    }
+}
+
+impl_attribute_format! {
+    i8  => Byte,
+    u8  => UnsignedByte,
+    i16 => Short,
+    u16 => UnsignedShort,
+    i32 => Int,
+    u32 => UnsignedInt,
+    f32 => Float,
+    f64 => Double
+}
+
+pub struct VertexArray {
+    id: Handle,
+}
+
+impl Drop for VertexArray {
+    fn drop(&mut self) {
+        unsafe { gl::DeleteVertexArrays(1, &self.id); }
+    }
+}
+
+impl GlObject for VertexArray {
+    #[inline]
+    fn id(&self) -> Handle {
+        self.id
+    }
+}
+
+impl VertexArray {
+    pub fn new() -> VertexArray {
+        let mut id = 0;
+        unsafe { gl::GenVertexArrays(1, &mut id); }
+
+        VertexArray {
+            id,
+        }
+    }
+
+    pub fn bind(&self) {
+        unsafe { gl::BindVertexArray(self.id); }
+    }
+
+    pub fn unbind(&self) {
+        unsafe { gl::BindVertexArray(0); }
+    }
+
+    // Configures the attributes in `layout` to read from `buffer`. The vertex array remembers the
+    // buffer, so this only needs to be done once.
+    pub fn attach_vertex_buffer<T: Copy>(&self, buffer: &Buffer<T>, layout: &VertexLayout) {
+        assert_eq!(buffer.target(), BufferTarget::Vertex);
+
+        self.bind();
+        buffer.bind();
+
+        for &(attribute, offset) in layout.attributes() {
+            unsafe {
+                if attribute.integer {
+                    gl::VertexAttribIPointer(attribute.location, attribute.components, attribute.ty.into(),
+                        layout.stride() as GLsizei, offset as *const GLvoid);
+                } else if attribute.ty == AttributeType::Double {
+                    // `VertexAttribPointer` would convert doubles to floats, losing the precision
+                    // they were used for.
+                    gl::VertexAttribLPointer(attribute.location, attribute.components, attribute.ty.into(),
+                        layout.stride() as GLsizei, offset as *const GLvoid);
+                } else {
+                    gl::VertexAttribPointer(attribute.location, attribute.components, attribute.ty.into(),
+                        attribute.normalized as GLboolean, layout.stride() as GLsizei, offset as *const GLvoid);
+                }
+
+                gl::EnableVertexAttribArray(attribute.location);
+            }
+        }
+
+        self.unbind();
+        buffer.unbind();
+    }
+
+    pub fn attach_vertices<V: Vertex>(&self, buffer: &Buffer<V>) {
+        self.attach_vertex_buffer(buffer, &V::layout());
+    }
+
+    // Like `attach_vertices`, but with the locations of named attributes looked up in `program`.
+    // See `VertexLayout::resolve`.
+    pub fn attach_vertices_for<V: Vertex>(&self, buffer: &Buffer<V>, program: &Program) {
+        self.attach_vertex_buffer(buffer, &V::layout().resolve(program));
+    }
+
+    pub fn attach_index_buffer<T: Copy>(&self, buffer: &Buffer<T>) {
+        assert_eq!(buffer.target(), BufferTarget::Index);
+
+        // The index buffer binding is part of the vertex array's state, so we must not unbind the
+        // buffer before unbinding the vertex array.
+        self.bind();
+        buffer.bind();
+        self.unbind();
+    }
+}
+
+impl Default for VertexArray {
+    fn default() -> VertexArray {
+        VertexArray::new()
+    }
+}
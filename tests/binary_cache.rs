@@ -0,0 +1,150 @@
+// Tests for `ProgramCache` against the recording fake in `tests/mock_gl`. Each test gets its own
+// cache directory under the system's temporary directory.
+extern crate learn_opengl as lgl;
+extern crate gl;
+
+mod mock_gl;
+
+use std::cell::Cell;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+use lgl::binary_cache::ProgramCache;
+use lgl::program::{Program, ProgramCreationError, Shader, ShaderType};
+
+use mock_gl::{Call, Script};
+
+const SOURCE: &'static str = "#version 330 core\nvoid main() {}\n";
+const OTHER_SOURCE: &'static str = "#version 330 core\nvoid main() { gl_Position = vec4(0.0); }\n";
+
+fn cache_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("learn_opengl_binary_cache_{}", name));
+    let _ = fs::remove_dir_all(&dir);
+
+    dir
+}
+
+fn entries(dir: &PathBuf) -> Vec<PathBuf> {
+    let mut entries: Vec<_> = fs::read_dir(dir)
+        .map(|entries| entries.map(|entry| entry.unwrap().path()).collect())
+        .unwrap_or_default();
+
+    entries.sort();
+    entries
+}
+
+// Gets the program for `source` from `cache`, and returns whether it had to be compiled.
+fn get(cache: &ProgramCache, source: &str) -> bool {
+    let compiled = Cell::new(false);
+
+    let program = cache.get_or_compile(&[(ShaderType::Vertex, source)], || -> Result<Program, ProgramCreationError> {
+        compiled.set(true);
+        Program::link(&[Shader::new(ShaderType::Vertex, source).unwrap()])
+    });
+
+    assert!(program.is_ok());
+    compiled.get()
+}
+
+#[test]
+fn cached_program_is_loaded_from_its_binary() {
+    let dir = cache_dir("loaded");
+    let cache = ProgramCache::new(&dir);
+
+    mock_gl::install(Script::default());
+    assert!(get(&cache, SOURCE));
+
+    // The entry holds the binary format, as a little-endian `u32`, followed by the binary.
+    let files = entries(&dir);
+    assert_eq!(files.len(), 1);
+    assert_eq!(fs::read(&files[0]).unwrap(), b"\x34\x12\0\0binary");
+
+    mock_gl::install(Script::default());
+    assert!(!get(&cache, SOURCE));
+    assert!(mock_gl::calls().contains(&Call::ProgramBinary(1, 0x1234, b"binary".to_vec())));
+}
+
+#[test]
+fn key_covers_sources_and_driver() {
+    let dir = cache_dir("key");
+    let cache = ProgramCache::new(&dir);
+
+    mock_gl::install(Script::default());
+    assert!(get(&cache, SOURCE));
+    assert!(get(&cache, OTHER_SOURCE));
+    assert_eq!(entries(&dir).len(), 2);
+
+    // A binary from another driver, or another version of the same driver, isn't used.
+    mock_gl::install(Script { renderer: "Other GL".to_string(), ..Script::default() });
+    assert!(get(&cache, SOURCE));
+
+    mock_gl::install(Script { version: "4.1 Mock 2.0".to_string(), ..Script::default() });
+    assert!(get(&cache, SOURCE));
+
+    assert_eq!(entries(&dir).len(), 4);
+}
+
+#[test]
+fn rejected_binary_is_recompiled_and_replaced() {
+    let dir = cache_dir("rejected");
+    let cache = ProgramCache::new(&dir);
+
+    mock_gl::install(Script::default());
+    assert!(get(&cache, SOURCE));
+
+    // After a driver update that keeps the same strings, the old binary no longer loads.
+    mock_gl::install(Script {
+        program_binary: (0x5678, b"new binary".to_vec()),
+        accept_program_binary: false,
+        ..Script::default()
+    });
+
+    assert!(get(&cache, SOURCE));
+
+    // The error raised by the rejected binary was drained.
+    assert_eq!(unsafe { gl::GetError() }, gl::NO_ERROR);
+
+    let files = entries(&dir);
+    assert_eq!(files.len(), 1);
+    assert_eq!(fs::read(&files[0]).unwrap(), b"\x78\x56\0\0new binary");
+}
+
+#[test]
+fn earlier_errors_are_not_taken_for_a_rejected_binary() {
+    mock_gl::install(Script::default());
+    mock_gl::raise_error(gl::INVALID_VALUE);
+
+    assert!(Program::from_binary(0x1234, b"binary").is_some());
+    assert_eq!(unsafe { gl::GetError() }, gl::NO_ERROR);
+}
+
+#[test]
+fn cache_is_bypassed_without_binary_formats() {
+    let dir = cache_dir("no_formats");
+    let cache = ProgramCache::new(&dir);
+
+    mock_gl::install(Script { num_program_binary_formats: 0, ..Script::default() });
+    assert!(get(&cache, SOURCE));
+    assert!(get(&cache, SOURCE));
+
+    assert!(entries(&dir).is_empty());
+    assert!(!mock_gl::calls().iter().any(|call| match *call {
+        Call::GetProgramBinary(..) | Call::ProgramBinary(..) => true,
+        _ => false,
+    }));
+}
+
+#[test]
+fn clear_removes_entries() {
+    let dir = cache_dir("clear");
+    let cache = ProgramCache::new(&dir);
+
+    mock_gl::install(Script::default());
+    assert!(get(&cache, SOURCE));
+
+    cache.clear().unwrap();
+    assert!(entries(&dir).is_empty());
+
+    assert!(get(&cache, SOURCE));
+}
@@ -0,0 +1,73 @@
+// Tests for the debug message callback against the recording fake in `tests/mock_gl`, for drivers
+// with KHR_debug, with only ARB_debug_output, and with neither.
+extern crate learn_opengl as lgl;
+extern crate gl;
+
+mod mock_gl;
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use lgl::debug::{self, DebugOptions, DebugSeverity};
+
+use mock_gl::{Call, Script};
+
+// Installs a callback that collects the messages it gets. Returns whether it was installed, and
+// the messages.
+fn install(script: Script, options: DebugOptions) -> (bool, Rc<RefCell<Vec<String>>>) {
+    mock_gl::install(script);
+
+    let messages = Rc::new(RefCell::new(Vec::new()));
+    let received = messages.clone();
+    let installed = debug::install_callback(options, move |message| received.borrow_mut().push(message.message.clone()));
+
+    (installed, messages)
+}
+
+#[test]
+fn khr_debug_enables_debug_output() {
+    let (installed, messages) = install(Script { version: "4.3 Mock".to_string(), ..Script::default() },
+                                        DebugOptions::new());
+
+    assert!(installed);
+    assert_eq!(mock_gl::calls(), vec![
+        Call::Enable(gl::DEBUG_OUTPUT),
+        Call::Enable(gl::DEBUG_OUTPUT_SYNCHRONOUS),
+        Call::DebugMessageCallback(true),
+    ]);
+
+    assert!(mock_gl::send_debug_message(gl::DEBUG_TYPE_ERROR, 1, gl::DEBUG_SEVERITY_HIGH, "invalid enum"));
+    assert_eq!(*messages.borrow(), ["invalid enum"]);
+
+    debug::remove_callback();
+    assert_eq!(&mock_gl::calls()[3..], &[Call::DebugMessageCallback(false), Call::Disable(gl::DEBUG_OUTPUT)]);
+}
+
+#[test]
+fn arb_debug_output_is_used_without_khr_debug() {
+    let script = Script { extensions: vec!["GL_ARB_debug_output".to_string()], ..Script::default() };
+    let (installed, messages) = install(script, DebugOptions::new().min_severity(DebugSeverity::Medium));
+
+    // ARB_debug_output has no `GL_DEBUG_OUTPUT` to enable.
+    assert!(installed);
+    assert_eq!(mock_gl::calls(), vec![
+        Call::Enable(gl::DEBUG_OUTPUT_SYNCHRONOUS),
+        Call::DebugMessageCallback(true),
+    ]);
+
+    mock_gl::send_debug_message(gl::DEBUG_TYPE_PERFORMANCE, 1, gl::DEBUG_SEVERITY_LOW, "slow path");
+    mock_gl::send_debug_message(gl::DEBUG_TYPE_ERROR, 2, gl::DEBUG_SEVERITY_HIGH, "invalid enum");
+    assert_eq!(*messages.borrow(), ["invalid enum"]);
+
+    debug::remove_callback();
+    assert_eq!(mock_gl::calls().last(), Some(&Call::DebugMessageCallback(false)));
+}
+
+#[test]
+fn callback_is_not_installed_without_debug_extensions() {
+    let script = Script { extensions: vec!["GL_ARB_texture_storage".to_string()], ..Script::default() };
+    let (installed, _) = install(script, DebugOptions::new());
+
+    assert!(!installed);
+    assert!(mock_gl::calls().is_empty());
+}
@@ -0,0 +1,175 @@
+// Golden-image tests for the examples. Each test draws an example's scene for a few frames in a
+// headless context, reads the result back and compares it to a reference image in
+// `tests/golden`.
+//
+// To create or update the reference images, run the tests with `UPDATE_GOLDEN=1` and check the
+// new images before committing them. When a test fails, its output and an image of the
+// differences (mismatching pixels in red, over a dimmed copy of the reference) are saved to
+// `target/golden`.
+//
+// The tests need a headless OpenGL 3.3 implementation, eg. OSMesa with Mesa's llvmpipe, which is
+// also what the reference images were rendered with. Most machines don't have one, so the tests
+// are ignored by default. Run them with:
+//
+//     cargo test --test golden -- --ignored
+//
+// Once asked for, they fail rather than pass without checking anything when there's no context.
+extern crate learn_opengl as lgl;
+extern crate learn_opengl_derive;
+extern crate gl;
+extern crate glutin;
+extern crate image;
+
+use std::env;
+use std::path::Path;
+use std::sync::Mutex;
+
+use lgl::create_headless_context;
+use lgl::screenshot::Screenshot;
+
+// The examples' sources are included as modules, so we can reuse their scenes.
+#[allow(dead_code)]
+#[path = "../src/bin/02_hello_triangle.rs"]
+mod hello_triangle;
+
+#[allow(dead_code)]
+#[path = "../src/bin/02b_hello_triangle.rs"]
+mod hello_triangle_b;
+
+#[allow(dead_code)]
+#[path = "../src/bin/03_hello_rectangle.rs"]
+mod hello_rectangle;
+
+#[allow(dead_code)]
+#[path = "../src/bin/04_shader_uniforms.rs"]
+mod shader_uniforms;
+
+#[allow(dead_code)]
+#[path = "../src/bin/05_colors_in_vertex_data.rs"]
+mod colors_in_vertex_data;
+
+#[allow(dead_code)]
+#[path = "../src/bin/06_textures.rs"]
+mod textures;
+
+const WIDTH: u32 = 320;
+const HEIGHT: u32 = 240;
+
+// Some examples only settle after a few frames, eg. when they stream data.
+const FRAMES: u32 = 3;
+
+// The largest difference allowed in each channel of each pixel. Different versions of a driver may
+// round interpolated colors and filtered texels differently.
+const TOLERANCE: u8 = 2;
+
+// The `gl` function pointers are global, so only one test may use a context at a time.
+static GL_LOCK: Mutex<()> = Mutex::new(());
+
+#[test]
+#[ignore = "needs a headless OpenGL context (OSMesa), run with `cargo test --test golden -- --ignored`"]
+fn hello_triangle() {
+    check_golden("02_hello_triangle", hello_triangle::Scene::new, |scene| scene.draw());
+}
+
+#[test]
+#[ignore = "needs a headless OpenGL context (OSMesa), run with `cargo test --test golden -- --ignored`"]
+fn hello_triangle_b() {
+    // Same triangle as `02_hello_triangle`, drawn through the library.
+    check_golden("02b_hello_triangle", hello_triangle_b::Scene::new, |scene| scene.draw());
+}
+
+#[test]
+#[ignore = "needs a headless OpenGL context (OSMesa), run with `cargo test --test golden -- --ignored`"]
+fn hello_rectangle() {
+    check_golden("03_hello_rectangle", hello_rectangle::Scene::new, |scene| scene.draw());
+}
+
+#[test]
+#[ignore = "needs a headless OpenGL context (OSMesa), run with `cargo test --test golden -- --ignored`"]
+fn shader_uniforms() {
+    // The color changes over time, so we draw it at a fixed time.
+    check_golden("04_shader_uniforms", shader_uniforms::Scene::new, |scene| scene.draw(1.0));
+}
+
+#[test]
+#[ignore = "needs a headless OpenGL context (OSMesa), run with `cargo test --test golden -- --ignored`"]
+fn colors_in_vertex_data() {
+    check_golden("05_colors_in_vertex_data", colors_in_vertex_data::Scene::new, |scene| scene.draw());
+}
+
+#[test]
+#[ignore = "needs a headless OpenGL context (OSMesa), run with `cargo test --test golden -- --ignored`"]
+fn textures() {
+    check_golden("06_textures", textures::Scene::new, |scene| scene.draw());
+}
+
+fn check_golden<S, F: Fn(&S)>(name: &str, setup: fn() -> S, draw: F) {
+    let _lock = GL_LOCK.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+
+    // The scene is declared after the context, so it's dropped first, while the context still
+    // exists.
+    let _context = match create_headless_context(WIDTH, HEIGHT) {
+        Ok(context) => context,
+        Err(e) => panic!("Couldn't create a headless context for {}: {:?}. The golden-image tests need \
+            OSMesa, eg. Mesa's llvmpipe.", name, e),
+    };
+
+    let scene = setup();
+
+    for _ in 0..FRAMES {
+        draw(&scene);
+    }
+
+    let actual = Screenshot::capture(WIDTH, HEIGHT);
+    let reference_path = Path::new("tests/golden").join(format!("{}.png", name));
+
+    if env::var_os("UPDATE_GOLDEN").is_some() {
+        actual.save(&reference_path).unwrap();
+        println!("Updated {}", reference_path.display());
+        return;
+    }
+
+    let reference = match image::open(&reference_path) {
+        Ok(reference) => reference.to_rgb(),
+        Err(e) => panic!("Couldn't open the reference image {}: {:?}. Run the tests with UPDATE_GOLDEN=1 to \
+            create it.", reference_path.display(), e),
+    };
+
+    assert_eq!(reference.dimensions(), (WIDTH, HEIGHT),
+        "{} doesn't have the size of the rendered image", reference_path.display());
+
+    let (mismatches, diff) = compare(actual.pixels(), &reference.into_raw());
+
+    if mismatches > 0 {
+        let output_dir = Path::new("target/golden");
+        let actual_path = output_dir.join(format!("{}-actual.png", name));
+        let diff_path = output_dir.join(format!("{}-diff.png", name));
+
+        actual.save(&actual_path).unwrap();
+        image::save_buffer(&diff_path, &diff, WIDTH, HEIGHT, image::ColorType::RGB(8)).unwrap();
+
+        panic!("{} pixels differ from {} by more than {}. See {} and {}.", mismatches,
+            reference_path.display(), TOLERANCE, actual_path.display(), diff_path.display());
+    }
+}
+
+// Compares two RGB images, returning the number of pixels that differ by more than `TOLERANCE`
+// and an image highlighting them.
+fn compare(actual: &[u8], reference: &[u8]) -> (usize, Vec<u8>) {
+    let mut mismatches = 0;
+    let mut diff = Vec::with_capacity(reference.len());
+
+    for (a, r) in actual.chunks(3).zip(reference.chunks(3)) {
+        let differs = a.iter().zip(r).any(|(&a, &r)| (a as i16 - r as i16).abs() > TOLERANCE as i16);
+
+        if differs {
+            mismatches += 1;
+            diff.extend_from_slice(&[255, 0, 0]);
+        } else {
+            let gray = ((r[0] as u16 + r[1] as u16 + r[2] as u16) / 12) as u8;
+            diff.extend_from_slice(&[gray, gray, gray]);
+        }
+    }
+
+    (mismatches, diff)
+}
@@ -0,0 +1,485 @@
+// A recording fake of the OpenGL functions used by `Program`, `Shader`, `ProgramCache`,
+// `RenderState` and the debug callback, so they can be tested without a context.
+//
+// The `gl` crate calls OpenGL through a table of function pointers, filled in by `gl::load_with`.
+// `install` fills it with the fakes below instead of a driver's functions. Each fake records its
+// call, and answers queries from the script passed to `install`. Functions without a fake are left
+// unloaded, and panic if called.
+//
+// The table is global, but the recorded calls and the script are per thread, so tests using the
+// fake can run in parallel. They must not share a test binary with tests using a real context.
+#![allow(dead_code)]
+
+use gl;
+use gl::types::*;
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::collections::VecDeque;
+use std::ffi::{CStr, CString};
+use std::os::raw::c_void;
+use std::ptr;
+use std::slice;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Call {
+    CreateShader(GLenum),
+    ShaderSource(GLuint, String),
+    CompileShader(GLuint),
+    GetShaderiv(GLuint, GLenum),
+    GetShaderInfoLog(GLuint),
+    DeleteShader(GLuint),
+    CreateProgram,
+    AttachShader(GLuint, GLuint),
+    ProgramParameteri(GLuint, GLenum, GLint),
+    LinkProgram(GLuint),
+    GetProgramiv(GLuint, GLenum),
+    GetProgramInfoLog(GLuint),
+    ProgramBinary(GLuint, GLenum, Vec<u8>),
+    GetProgramBinary(GLuint),
+    UseProgram(GLuint),
+    DeleteProgram(GLuint),
+    GetUniformLocation(GLuint, String),
+    Uniform4f(GLint, [GLfloat; 4]),
+    Enable(GLenum),
+    Disable(GLenum),
+    DepthFunc(GLenum),
+    DepthMask(bool),
+    StencilFunc(GLenum, GLint, GLuint),
+    StencilOp(GLenum, GLenum, GLenum),
+    StencilMask(GLuint),
+    BlendEquationSeparate(GLenum, GLenum),
+    BlendFuncSeparate(GLenum, GLenum, GLenum, GLenum),
+    BlendColor([GLfloat; 4]),
+    CullFace(GLenum),
+    FrontFace(GLenum),
+    PolygonMode(GLenum, GLenum),
+    ColorMask([bool; 4]),
+    // Whether the callback was given a user parameter, ie. whether it was installed or removed.
+    DebugMessageCallback(bool),
+}
+
+// What the fake driver answers. Info logs are raw bytes, as a driver would write them, so they
+// must include the terminating NUL unless a test is about a broken log.
+#[derive(Clone, Debug)]
+pub struct Script {
+    pub compile_status: bool,
+    pub shader_info_log: Vec<u8>,
+    pub link_status: bool,
+    pub program_info_log: Vec<u8>,
+    // Names missing from the map are reported as not found, ie. -1.
+    pub uniform_locations: HashMap<String, GLint>,
+    // What `glGetString` returns for `GL_VENDOR`, `GL_RENDERER` and `GL_VERSION`. `glGetIntegerv`
+    // takes `GL_MAJOR_VERSION` and `GL_MINOR_VERSION` from the start of `version`.
+    pub vendor: String,
+    pub renderer: String,
+    pub version: String,
+    // What `glGetStringi` returns for `GL_EXTENSIONS`.
+    pub extensions: Vec<String>,
+    pub num_program_binary_formats: GLint,
+    // The format and binary returned by `glGetProgramBinary`.
+    pub program_binary: (GLenum, Vec<u8>),
+    // When false, `glProgramBinary` fails to link the program and raises `GL_INVALID_ENUM`.
+    pub accept_program_binary: bool,
+}
+
+impl Default for Script {
+    fn default() -> Script {
+        Script {
+            compile_status: true,
+            shader_info_log: b"\0".to_vec(),
+            link_status: true,
+            program_info_log: b"\0".to_vec(),
+            uniform_locations: HashMap::new(),
+            vendor: "Mock".to_string(),
+            renderer: "Mock GL".to_string(),
+            version: "4.1 Mock".to_string(),
+            extensions: Vec::new(),
+            num_program_binary_formats: 1,
+            program_binary: (0x1234, b"binary".to_vec()),
+            accept_program_binary: true,
+        }
+    }
+}
+
+struct State {
+    script: Script,
+    calls: Vec<Call>,
+    next_id: GLuint,
+    // Programs whose binary was rejected, which report a failed link.
+    rejected_programs: Vec<GLuint>,
+    errors: VecDeque<GLenum>,
+    // Keeps the strings returned by `glGetString` alive until the next `install`.
+    strings: Vec<CString>,
+    debug_callback: Option<(GLDEBUGPROC, *const c_void)>,
+}
+
+impl State {
+    fn new(script: Script) -> State {
+        State {
+            script,
+            calls: Vec::new(),
+            next_id: 1,
+            rejected_programs: Vec::new(),
+            errors: VecDeque::new(),
+            strings: Vec::new(),
+            debug_callback: None,
+        }
+    }
+}
+
+thread_local!(static STATE: RefCell<State> = RefCell::new(State::new(Script::default())));
+
+// Loads the fakes into the `gl` function table, and starts a new recording with `script`.
+pub fn install(script: Script) {
+    STATE.with(|state| *state.borrow_mut() = State::new(script));
+
+    gl::load_with(|name| match name {
+        "glCreateShader"          => create_shader as *const c_void,
+        "glShaderSource"          => shader_source as *const c_void,
+        "glCompileShader"         => compile_shader as *const c_void,
+        "glGetShaderiv"           => get_shader_iv as *const c_void,
+        "glGetShaderInfoLog"      => get_shader_info_log as *const c_void,
+        "glDeleteShader"          => delete_shader as *const c_void,
+        "glCreateProgram"         => create_program as *const c_void,
+        "glAttachShader"          => attach_shader as *const c_void,
+        "glProgramParameteri"     => program_parameter_i as *const c_void,
+        "glLinkProgram"           => link_program as *const c_void,
+        "glGetProgramiv"          => get_program_iv as *const c_void,
+        "glGetProgramInfoLog"     => get_program_info_log as *const c_void,
+        "glProgramBinary"         => program_binary as *const c_void,
+        "glGetProgramBinary"      => get_program_binary as *const c_void,
+        "glGetString"             => get_string as *const c_void,
+        "glGetIntegerv"           => get_integer_v as *const c_void,
+        "glGetStringi"            => get_string_i as *const c_void,
+        "glGetError"              => get_error as *const c_void,
+        "glUseProgram"            => use_program as *const c_void,
+        "glDeleteProgram"         => delete_program as *const c_void,
+        "glGetUniformLocation"    => get_uniform_location as *const c_void,
+        "glUniform4f"             => uniform_4f as *const c_void,
+        "glEnable"                => enable as *const c_void,
+        "glDisable"               => disable as *const c_void,
+        "glDepthFunc"             => depth_func as *const c_void,
+        "glDepthMask"             => depth_mask as *const c_void,
+        "glStencilFunc"           => stencil_func as *const c_void,
+        "glStencilOp"             => stencil_op as *const c_void,
+        "glStencilMask"           => stencil_mask as *const c_void,
+        "glBlendEquationSeparate" => blend_equation_separate as *const c_void,
+        "glBlendFuncSeparate"     => blend_func_separate as *const c_void,
+        "glBlendColor"            => blend_color as *const c_void,
+        "glCullFace"              => cull_face as *const c_void,
+        "glFrontFace"             => front_face as *const c_void,
+        "glPolygonMode"           => polygon_mode as *const c_void,
+        "glColorMask"             => color_mask as *const c_void,
+        "glDebugMessageCallback"  => debug_message_callback as *const c_void,
+        _                         => ptr::null(),
+    });
+}
+
+// The calls recorded since `install`, in order.
+pub fn calls() -> Vec<Call> {
+    STATE.with(|state| state.borrow().calls.clone())
+}
+
+// Delivers a message to the installed debug callback, as a driver would. Returns false if there is
+// no callback.
+pub fn send_debug_message(ty: GLenum, id: GLuint, severity: GLenum, message: &str) -> bool {
+    let callback = STATE.with(|state| state.borrow().debug_callback);
+
+    match callback {
+        Some((callback, user_param)) => {
+            callback(gl::DEBUG_SOURCE_API, ty, id, severity, message.len() as GLsizei,
+                     message.as_ptr() as *const GLchar, user_param as *mut c_void);
+            true
+        }
+        None => false,
+    }
+}
+
+// Queues an error for `glGetError`, as if an earlier call had failed.
+pub fn raise_error(error: GLenum) {
+    STATE.with(|state| state.borrow_mut().errors.push_back(error));
+}
+
+fn record(call: Call) {
+    STATE.with(|state| state.borrow_mut().calls.push(call));
+}
+
+fn script() -> Script {
+    STATE.with(|state| state.borrow().script.clone())
+}
+
+fn next_id() -> GLuint {
+    STATE.with(|state| {
+        let mut state = state.borrow_mut();
+        state.next_id += 1;
+        state.next_id - 1
+    })
+}
+
+// Copies `log` into a buffer of `buf_size` bytes, like `glGet*InfoLog`.
+unsafe fn write_info_log(log: &[u8], buf_size: GLsizei, length: *mut GLsizei, info_log: *mut GLchar) {
+    let len = log.len().min(buf_size.max(0) as usize);
+    ptr::copy_nonoverlapping(log.as_ptr(), info_log as *mut u8, len);
+
+    if !length.is_null() {
+        // The written length excludes the NUL.
+        *length = log[..len].iter().take_while(|&&b| b != 0).count() as GLsizei;
+    }
+}
+
+unsafe fn c_str(s: *const GLchar) -> String {
+    CStr::from_ptr(s).to_string_lossy().into_owned()
+}
+
+extern "system" fn create_shader(ty: GLenum) -> GLuint {
+    record(Call::CreateShader(ty));
+    next_id()
+}
+
+extern "system" fn shader_source(shader: GLuint, count: GLsizei, strings: *const *const GLchar, lengths: *const GLint) {
+    let source = unsafe {
+        let strings = slice::from_raw_parts(strings, count as usize);
+
+        // A null `lengths`, or a negative length, means the string is NUL-terminated.
+        let lengths = if lengths.is_null() {
+            vec![-1; strings.len()]
+        } else {
+            slice::from_raw_parts(lengths, count as usize).to_vec()
+        };
+
+        strings.iter().zip(lengths)
+            .map(|(&s, len)| if len < 0 {
+                c_str(s)
+            } else {
+                String::from_utf8_lossy(slice::from_raw_parts(s as *const u8, len as usize)).into_owned()
+            })
+            .collect()
+    };
+
+    record(Call::ShaderSource(shader, source));
+}
+
+extern "system" fn compile_shader(shader: GLuint) {
+    record(Call::CompileShader(shader));
+}
+
+extern "system" fn get_shader_iv(shader: GLuint, pname: GLenum, params: *mut GLint) {
+    record(Call::GetShaderiv(shader, pname));
+
+    let script = script();
+    let value = match pname {
+        gl::COMPILE_STATUS  => script.compile_status as GLint,
+        gl::INFO_LOG_LENGTH => script.shader_info_log.len() as GLint,
+        _ => panic!("mock GetShaderiv doesn't support pname {:#x}", pname),
+    };
+
+    unsafe { *params = value; }
+}
+
+extern "system" fn get_shader_info_log(shader: GLuint, buf_size: GLsizei, length: *mut GLsizei, info_log: *mut GLchar) {
+    record(Call::GetShaderInfoLog(shader));
+    unsafe { write_info_log(&script().shader_info_log, buf_size, length, info_log); }
+}
+
+extern "system" fn delete_shader(shader: GLuint) {
+    record(Call::DeleteShader(shader));
+}
+
+extern "system" fn create_program() -> GLuint {
+    record(Call::CreateProgram);
+    next_id()
+}
+
+extern "system" fn attach_shader(program: GLuint, shader: GLuint) {
+    record(Call::AttachShader(program, shader));
+}
+
+extern "system" fn program_parameter_i(program: GLuint, pname: GLenum, value: GLint) {
+    record(Call::ProgramParameteri(program, pname, value));
+}
+
+extern "system" fn link_program(program: GLuint) {
+    record(Call::LinkProgram(program));
+}
+
+extern "system" fn get_program_iv(program: GLuint, pname: GLenum, params: *mut GLint) {
+    record(Call::GetProgramiv(program, pname));
+
+    let script = script();
+    let rejected = STATE.with(|state| state.borrow().rejected_programs.contains(&program));
+
+    let value = match pname {
+        gl::LINK_STATUS           => (script.link_status && !rejected) as GLint,
+        gl::INFO_LOG_LENGTH       => script.program_info_log.len() as GLint,
+        gl::PROGRAM_BINARY_LENGTH => script.program_binary.1.len() as GLint,
+        _ => panic!("mock GetProgramiv doesn't support pname {:#x}", pname),
+    };
+
+    unsafe { *params = value; }
+}
+
+extern "system" fn get_program_info_log(program: GLuint, buf_size: GLsizei, length: *mut GLsizei, info_log: *mut GLchar) {
+    record(Call::GetProgramInfoLog(program));
+    unsafe { write_info_log(&script().program_info_log, buf_size, length, info_log); }
+}
+
+extern "system" fn program_binary(program: GLuint, format: GLenum, binary: *const c_void, length: GLsizei) {
+    let binary = unsafe { slice::from_raw_parts(binary as *const u8, length as usize) };
+    record(Call::ProgramBinary(program, format, binary.to_vec()));
+
+    if !script().accept_program_binary {
+        STATE.with(|state| {
+            let mut state = state.borrow_mut();
+            state.rejected_programs.push(program);
+            state.errors.push_back(gl::INVALID_ENUM);
+        });
+    }
+}
+
+extern "system" fn get_program_binary(program: GLuint, buf_size: GLsizei, length: *mut GLsizei, format: *mut GLenum,
+                                      binary: *mut c_void) {
+    record(Call::GetProgramBinary(program));
+
+    let (binary_format, data) = script().program_binary;
+    let len = data.len().min(buf_size.max(0) as usize);
+
+    unsafe {
+        ptr::copy_nonoverlapping(data.as_ptr(), binary as *mut u8, len);
+        *format = binary_format;
+
+        if !length.is_null() {
+            *length = len as GLsizei;
+        }
+    }
+}
+
+extern "system" fn get_string(name: GLenum) -> *const GLubyte {
+    let script = script();
+    let string = match name {
+        gl::VENDOR   => script.vendor,
+        gl::RENDERER => script.renderer,
+        gl::VERSION  => script.version,
+        _ => panic!("mock GetString doesn't support name {:#x}", name),
+    };
+
+    let string = CString::new(string).unwrap();
+    let ptr = string.as_ptr() as *const GLubyte;
+    STATE.with(|state| state.borrow_mut().strings.push(string));
+
+    ptr
+}
+
+extern "system" fn get_integer_v(pname: GLenum, params: *mut GLint) {
+    let script = script();
+    let version = |index: usize| -> GLint {
+        script.version.split(|c| c == '.' || c == ' ').nth(index).unwrap().parse().unwrap()
+    };
+
+    let value = match pname {
+        gl::NUM_PROGRAM_BINARY_FORMATS => script.num_program_binary_formats,
+        gl::MAJOR_VERSION              => version(0),
+        gl::MINOR_VERSION              => version(1),
+        gl::NUM_EXTENSIONS             => script.extensions.len() as GLint,
+        _ => panic!("mock GetIntegerv doesn't support pname {:#x}", pname),
+    };
+
+    unsafe { *params = value; }
+}
+
+extern "system" fn get_string_i(name: GLenum, index: GLuint) -> *const GLubyte {
+    assert_eq!(name, gl::EXTENSIONS, "mock GetStringi only supports GL_EXTENSIONS");
+
+    let string = CString::new(script().extensions[index as usize].clone()).unwrap();
+    let ptr = string.as_ptr() as *const GLubyte;
+    STATE.with(|state| state.borrow_mut().strings.push(string));
+
+    ptr
+}
+
+extern "system" fn get_error() -> GLenum {
+    STATE.with(|state| state.borrow_mut().errors.pop_front().unwrap_or(gl::NO_ERROR))
+}
+
+extern "system" fn use_program(program: GLuint) {
+    record(Call::UseProgram(program));
+}
+
+extern "system" fn delete_program(program: GLuint) {
+    record(Call::DeleteProgram(program));
+}
+
+extern "system" fn get_uniform_location(program: GLuint, name: *const GLchar) -> GLint {
+    let name = unsafe { c_str(name) };
+    record(Call::GetUniformLocation(program, name.clone()));
+
+    script().uniform_locations.get(&name).cloned().unwrap_or(-1)
+}
+
+extern "system" fn uniform_4f(location: GLint, x: GLfloat, y: GLfloat, z: GLfloat, w: GLfloat) {
+    record(Call::Uniform4f(location, [x, y, z, w]));
+}
+
+extern "system" fn enable(cap: GLenum) {
+    record(Call::Enable(cap));
+}
+
+extern "system" fn disable(cap: GLenum) {
+    record(Call::Disable(cap));
+}
+
+extern "system" fn depth_func(func: GLenum) {
+    record(Call::DepthFunc(func));
+}
+
+extern "system" fn depth_mask(flag: GLboolean) {
+    record(Call::DepthMask(flag == gl::TRUE));
+}
+
+extern "system" fn stencil_func(func: GLenum, reference: GLint, mask: GLuint) {
+    record(Call::StencilFunc(func, reference, mask));
+}
+
+extern "system" fn stencil_op(fail: GLenum, depth_fail: GLenum, pass: GLenum) {
+    record(Call::StencilOp(fail, depth_fail, pass));
+}
+
+extern "system" fn stencil_mask(mask: GLuint) {
+    record(Call::StencilMask(mask));
+}
+
+extern "system" fn blend_equation_separate(color: GLenum, alpha: GLenum) {
+    record(Call::BlendEquationSeparate(color, alpha));
+}
+
+extern "system" fn blend_func_separate(src_rgb: GLenum, dst_rgb: GLenum, src_alpha: GLenum, dst_alpha: GLenum) {
+    record(Call::BlendFuncSeparate(src_rgb, dst_rgb, src_alpha, dst_alpha));
+}
+
+extern "system" fn blend_color(r: GLfloat, g: GLfloat, b: GLfloat, a: GLfloat) {
+    record(Call::BlendColor([r, g, b, a]));
+}
+
+extern "system" fn cull_face(mode: GLenum) {
+    record(Call::CullFace(mode));
+}
+
+extern "system" fn front_face(mode: GLenum) {
+    record(Call::FrontFace(mode));
+}
+
+extern "system" fn polygon_mode(face: GLenum, mode: GLenum) {
+    record(Call::PolygonMode(face, mode));
+}
+
+extern "system" fn color_mask(r: GLboolean, g: GLboolean, b: GLboolean, a: GLboolean) {
+    record(Call::ColorMask([r == gl::TRUE, g == gl::TRUE, b == gl::TRUE, a == gl::TRUE]));
+}
+
+extern "system" fn debug_message_callback(callback: GLDEBUGPROC, user_param: *const c_void) {
+    record(Call::DebugMessageCallback(!user_param.is_null()));
+
+    STATE.with(|state| {
+        state.borrow_mut().debug_callback = if user_param.is_null() { None } else { Some((callback, user_param)) };
+    });
+}
@@ -0,0 +1,371 @@
+// Tests for `Shader` and `Program` against the recording fake in `tests/mock_gl`, so they run
+// without a context. Each test scripts what the driver answers, then checks the result and the
+// calls that were made.
+extern crate learn_opengl as lgl;
+extern crate gl;
+
+mod mock_gl;
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use gl::types::*;
+
+use lgl::diagnostics::Severity;
+use lgl::program::{FileCompiler, FileCompilerError, Program, ProgramCreationError, Shader, ShaderCreationError,
+                   ShaderType, SourceCompiler, UniformError, WatchingCompiler};
+
+use mock_gl::{Call, Script};
+
+const SOURCE: &'static str = "#version 330 core\nvoid main() {}\n";
+
+fn compile(script: Script) -> Result<Shader, ShaderCreationError> {
+    mock_gl::install(script);
+    Shader::new(ShaderType::Vertex, SOURCE)
+}
+
+#[test]
+fn shader_compiles() {
+    let shader = compile(Script::default());
+
+    assert!(shader.is_ok());
+    assert_eq!(mock_gl::calls(), vec![
+        Call::CreateShader(gl::VERTEX_SHADER),
+        Call::ShaderSource(1, SOURCE.to_string()),
+        Call::CompileShader(1),
+        Call::GetShaderiv(1, gl::COMPILE_STATUS),
+    ]);
+
+    drop(shader);
+    assert_eq!(mock_gl::calls().last(), Some(&Call::DeleteShader(1)));
+}
+
+#[test]
+fn shader_compile_error_is_parsed() {
+    let result = compile(Script {
+        compile_status: false,
+        shader_info_log: b"0:3(10): error: `foo' undeclared\n\0".to_vec(),
+        ..Script::default()
+    });
+
+    match result {
+        Err(ShaderCreationError::CompileError(log)) => {
+            assert_eq!(log.diagnostics.len(), 1);
+
+            let diagnostic = &log.diagnostics[0];
+            assert_eq!(diagnostic.severity, Severity::Error);
+            assert_eq!(diagnostic.line, Some(3));
+            assert_eq!(diagnostic.column, Some(10));
+            assert_eq!(diagnostic.message, "`foo' undeclared");
+        }
+        other => panic!("expected a compile error, got {:?}", other.err()),
+    }
+
+    // The failed shader is deleted.
+    assert_eq!(mock_gl::calls().last(), Some(&Call::DeleteShader(1)));
+}
+
+#[test]
+fn shader_info_log_without_nul_is_invalid() {
+    let result = compile(Script {
+        compile_status: false,
+        shader_info_log: b"0:3(10): error: `foo' undeclared".to_vec(),
+        ..Script::default()
+    });
+
+    match result {
+        Err(ShaderCreationError::InvalidInfoLog) => {}
+        other => panic!("expected an invalid info log, got {:?}", other.err()),
+    }
+
+    assert_eq!(mock_gl::calls().last(), Some(&Call::DeleteShader(1)));
+}
+
+#[test]
+fn shader_source_with_nul_is_invalid() {
+    mock_gl::install(Script::default());
+
+    match Shader::new(ShaderType::Fragment, "void main() {}\0") {
+        Err(ShaderCreationError::InvalidSource) => {}
+        other => panic!("expected an invalid source, got {:?}", other.err()),
+    }
+
+    // The source is checked before any shader object is created.
+    assert_eq!(mock_gl::calls(), vec![]);
+}
+
+fn link(script: Script) -> Result<Program, ProgramCreationError> {
+    mock_gl::install(script);
+
+    let shaders = [
+        Shader::new(ShaderType::Vertex, SOURCE).unwrap(),
+        Shader::new(ShaderType::Fragment, SOURCE).unwrap(),
+    ];
+
+    Program::link(&shaders)
+}
+
+#[test]
+fn program_links() {
+    let program = link(Script::default());
+
+    assert!(program.is_ok());
+
+    let calls = mock_gl::calls();
+    assert!(calls.contains(&Call::AttachShader(3, 1)));
+    assert!(calls.contains(&Call::AttachShader(3, 2)));
+    assert!(calls.contains(&Call::LinkProgram(3)));
+
+    // Asked for before linking, so that the program binary can be cached.
+    let hint = calls.iter().position(|call| *call == Call::ProgramParameteri(3, gl::PROGRAM_BINARY_RETRIEVABLE_HINT, 1));
+    let link = calls.iter().position(|call| *call == Call::LinkProgram(3));
+    assert!(hint.is_some() && hint < link);
+
+    // The shaders can be deleted once the program is linked.
+    assert!(calls.contains(&Call::DeleteShader(1)));
+    assert!(calls.contains(&Call::DeleteShader(2)));
+
+    drop(program);
+    assert_eq!(&mock_gl::calls()[calls.len()..], &[Call::UseProgram(0), Call::DeleteProgram(3)]);
+}
+
+#[test]
+fn program_link_error_is_parsed() {
+    let result = link(Script {
+        link_status: false,
+        program_info_log: b"error: vertex shader output `color' not read by fragment shader\n\0".to_vec(),
+        ..Script::default()
+    });
+
+    match result {
+        Err(ProgramCreationError::LinkError(log)) => {
+            assert_eq!(log.diagnostics.len(), 1);
+            assert_eq!(log.diagnostics[0].severity, Severity::Error);
+            assert_eq!(log.diagnostics[0].line, None);
+        }
+        other => panic!("expected a link error, got {:?}", other.err()),
+    }
+
+    assert!(mock_gl::calls().contains(&Call::DeleteProgram(3)));
+}
+
+#[test]
+fn program_info_log_without_nul_is_invalid() {
+    let result = link(Script {
+        link_status: false,
+        program_info_log: b"error: linking failed".to_vec(),
+        ..Script::default()
+    });
+
+    match result {
+        Err(ProgramCreationError::InvalidInfoLog) => {}
+        other => panic!("expected an invalid info log, got {:?}", other.err()),
+    }
+
+    assert!(mock_gl::calls().contains(&Call::DeleteProgram(3)));
+}
+
+#[test]
+fn uniform_locations_are_cached() {
+    let mut script = Script::default();
+    script.uniform_locations.insert("our_color".to_string(), 4);
+
+    let program = link(script).unwrap();
+    let color: [GLfloat; 4] = [0.0, 0.5, 1.0, 1.0];
+
+    program.set_uniform("our_color", color).unwrap();
+    program.set_uniform("our_color", color).unwrap();
+
+    let calls = mock_gl::calls();
+    let lookups = calls.iter().filter(|call| match **call {
+        Call::GetUniformLocation(..) => true,
+        _ => false,
+    });
+
+    assert_eq!(lookups.count(), 1);
+    assert!(calls.contains(&Call::UseProgram(3)));
+    assert!(calls.contains(&Call::Uniform4f(4, color)));
+}
+
+#[test]
+fn missing_uniform_is_not_found() {
+    let program = link(Script::default()).unwrap();
+
+    match program.uniform_location("missing") {
+        Err(UniformError::NotFound(ref name)) if name == "missing" => {}
+        other => panic!("expected the uniform not to be found, got {:?}", other),
+    }
+}
+
+#[test]
+fn source_compiler_resolves_includes() {
+    let include_path = env::temp_dir().join("learn_opengl_program_test_include.glsl");
+    fs::write(&include_path, "float half(float x) { return x * 0.5; }\n").unwrap();
+
+    mock_gl::install(Script::default());
+
+    let source = format!("#version 330 core\n#include \"{}\"\nvoid main() {{}}\n", include_path.display());
+    SourceCompiler::compile(&[(ShaderType::Vertex, &source)]).unwrap();
+
+    let expected = "#version 330 core\n#line 1 1\nfloat half(float x) { return x * 0.5; }\n#line 3 0\nvoid main() {}\n";
+    assert!(mock_gl::calls().contains(&Call::ShaderSource(1, expected.to_string())));
+}
+
+fn shader_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("learn_opengl_program_test_{}", name));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+
+    dir
+}
+
+// Writes `contents` to `path`, with a modification time `seconds` after the epoch, so that changes
+// are seen regardless of the file system's timestamp resolution.
+fn write_file(path: &Path, contents: &str, seconds: u64) {
+    fs::write(path, contents).unwrap();
+
+    let file = fs::OpenOptions::new().write(true).open(path).unwrap();
+    file.set_modified(SystemTime::UNIX_EPOCH + Duration::from_secs(seconds)).unwrap();
+}
+
+const FRAGMENT_SOURCE: &'static str = "#version 330 core\nout vec4 color;\nvoid main() { color = vec4(1.0); }\n";
+const NEW_SOURCE: &'static str = "#version 330 core\nvoid main() { gl_Position = vec4(0.0); }\n";
+
+// Writes a vertex and a fragment shader into a new directory, and returns their paths.
+fn write_shaders(name: &str) -> (PathBuf, PathBuf) {
+    let dir = shader_dir(name);
+    let vertex_path = dir.join("shader.vert");
+    let fragment_path = dir.join("shader.frag");
+
+    write_file(&vertex_path, SOURCE, 1);
+    write_file(&fragment_path, FRAGMENT_SOURCE, 1);
+
+    (vertex_path, fragment_path)
+}
+
+#[test]
+fn file_compiler_compiles_files() {
+    let (vertex_path, fragment_path) = write_shaders("file_compiler");
+
+    mock_gl::install(Script::default());
+    FileCompiler::compile(&[&vertex_path, &fragment_path]).unwrap();
+
+    assert_eq!(&mock_gl::calls()[..10], &[
+        Call::CreateShader(gl::VERTEX_SHADER),
+        Call::ShaderSource(1, SOURCE.to_string()),
+        Call::CompileShader(1),
+        Call::GetShaderiv(1, gl::COMPILE_STATUS),
+        Call::CreateShader(gl::FRAGMENT_SHADER),
+        Call::ShaderSource(2, FRAGMENT_SOURCE.to_string()),
+        Call::CompileShader(2),
+        Call::GetShaderiv(2, gl::COMPILE_STATUS),
+        Call::CreateProgram,
+        Call::AttachShader(3, 1),
+    ]);
+
+    assert!(mock_gl::calls().contains(&Call::LinkProgram(3)));
+}
+
+#[test]
+fn file_compiler_rejects_unknown_extensions() {
+    let dir = shader_dir("unknown_extension");
+    let path = dir.join("shader.glsl");
+    write_file(&path, SOURCE, 1);
+
+    mock_gl::install(Script::default());
+
+    match FileCompiler::compile(&[&path]) {
+        Err(FileCompilerError::UnknownShaderType(unknown)) => assert_eq!(unknown, path),
+        _ => panic!("expected an unknown shader type"),
+    }
+
+    assert!(mock_gl::calls().is_empty());
+}
+
+#[test]
+fn unchanged_files_are_not_recompiled() {
+    let (vertex_path, fragment_path) = write_shaders("unchanged");
+
+    mock_gl::install(Script::default());
+    let mut compiler = WatchingCompiler::new(&[&vertex_path, &fragment_path]).unwrap();
+
+    mock_gl::install(Script::default());
+    assert!(!compiler.reload_if_changed().unwrap());
+    assert!(mock_gl::calls().is_empty());
+}
+
+#[test]
+fn modified_file_is_reloaded() {
+    let (vertex_path, fragment_path) = write_shaders("modified");
+
+    mock_gl::install(Script::default());
+    let mut compiler = WatchingCompiler::new(&[&vertex_path, &fragment_path]).unwrap();
+
+    write_file(&vertex_path, NEW_SOURCE, 2);
+
+    mock_gl::install(Script::default());
+    assert!(compiler.reload_if_changed().unwrap());
+    assert!(mock_gl::calls().contains(&Call::ShaderSource(1, NEW_SOURCE.to_string())));
+
+    // Until the next change, the new program is kept.
+    assert!(!compiler.reload_if_changed().unwrap());
+}
+
+#[test]
+fn failed_reload_keeps_previous_program() {
+    let (vertex_path, fragment_path) = write_shaders("failed_keeps_program");
+
+    mock_gl::install(Script::default());
+    // The shaders get the names 1 and 2, and the program 3.
+    let mut compiler = WatchingCompiler::new(&[&vertex_path, &fragment_path]).unwrap();
+
+    write_file(&vertex_path, "#version 330 core\nvoid main() { foo = 1; }\n", 2);
+
+    mock_gl::install(Script {
+        compile_status: false,
+        shader_info_log: b"0:2(15): error: `foo' undeclared\n\0".to_vec(),
+        ..Script::default()
+    });
+
+    match compiler.reload_if_changed() {
+        Err(FileCompilerError::ShaderCreationError(path, ShaderCreationError::CompileError(_))) => {
+            assert_eq!(path, vertex_path);
+        }
+        _ => panic!("expected a compile error"),
+    }
+
+    assert!(!mock_gl::calls().contains(&Call::DeleteProgram(3)));
+
+    compiler.program().activate();
+    assert_eq!(mock_gl::calls().last(), Some(&Call::UseProgram(3)));
+
+    // The same error isn't reported again until the files change.
+    assert!(!compiler.reload_if_changed().unwrap());
+}
+
+#[test]
+fn failed_reload_watches_new_includes() {
+    let dir = shader_dir("failed_reload");
+    let shader_path = dir.join("shader.vert");
+    let include_path = dir.join("common.glsl");
+
+    write_file(&shader_path, SOURCE, 1);
+
+    mock_gl::install(Script::default());
+    let mut compiler = WatchingCompiler::new(&[&shader_path]).unwrap();
+
+    // The shader now includes a new file, which has an error.
+    write_file(&include_path, "float half(float x) { return x * 0.5 }\n", 1);
+    write_file(&shader_path, "#version 330 core\n#include \"common.glsl\"\nvoid main() {}\n", 2);
+
+    mock_gl::install(Script { compile_status: false, ..Script::default() });
+    assert!(compiler.reload_if_changed().is_err());
+
+    // Fixing the included file is enough to trigger another attempt.
+    write_file(&include_path, "float half(float x) { return x * 0.5; }\n", 2);
+
+    mock_gl::install(Script::default());
+    assert!(compiler.reload_if_changed().unwrap());
+}
@@ -0,0 +1,135 @@
+// Tests for `RenderState::apply` against the recording fake in `tests/mock_gl`, checking that only
+// the state that changed is sent to OpenGL.
+extern crate learn_opengl as lgl;
+extern crate gl;
+
+mod mock_gl;
+
+use lgl::render_state::{self, Blend, Comparison, Face, PolygonMode, RenderState, Stencil, StencilOp};
+
+use mock_gl::{Call, Script};
+
+// Installs the fake and forgets what earlier tests on this thread applied.
+fn setup() {
+    mock_gl::install(Script::default());
+    render_state::invalidate();
+}
+
+#[test]
+fn first_apply_sets_everything() {
+    setup();
+
+    RenderState::default().apply();
+
+    assert_eq!(mock_gl::calls(), vec![
+        Call::Disable(gl::DEPTH_TEST),
+        Call::DepthMask(true),
+        Call::Disable(gl::STENCIL_TEST),
+        Call::StencilMask(!0),
+        Call::Disable(gl::BLEND),
+        Call::Disable(gl::CULL_FACE),
+        Call::FrontFace(gl::CCW),
+        Call::PolygonMode(gl::FRONT_AND_BACK, gl::FILL),
+        Call::ColorMask([true, true, true, true]),
+    ]);
+}
+
+#[test]
+fn applying_the_same_state_again_does_nothing() {
+    setup();
+
+    let state = RenderState {
+        depth_test: Some(Comparison::Less),
+        cull_face: Some(Face::Back),
+        ..RenderState::default()
+    };
+
+    state.apply();
+    let calls = mock_gl::calls().len();
+
+    state.apply();
+    assert_eq!(mock_gl::calls().len(), calls);
+}
+
+#[test]
+fn only_changed_fields_are_applied() {
+    setup();
+
+    let state = RenderState::default();
+    state.apply();
+    let calls = mock_gl::calls().len();
+
+    RenderState { polygon_mode: PolygonMode::Line, ..state }.apply();
+    RenderState { depth_test: Some(Comparison::LessOrEqual), depth_write: false, ..state }.apply();
+
+    assert_eq!(&mock_gl::calls()[calls..], &[
+        Call::PolygonMode(gl::FRONT_AND_BACK, gl::LINE),
+        Call::Enable(gl::DEPTH_TEST),
+        Call::DepthFunc(gl::LEQUAL),
+        Call::DepthMask(false),
+        Call::PolygonMode(gl::FRONT_AND_BACK, gl::FILL),
+    ]);
+}
+
+#[test]
+fn blending_is_enabled_and_disabled() {
+    setup();
+
+    let state = RenderState::default();
+    state.apply();
+    let calls = mock_gl::calls().len();
+
+    RenderState { blend: Some(Blend::alpha()), ..state }.apply();
+    state.apply();
+
+    assert_eq!(&mock_gl::calls()[calls..], &[
+        Call::Enable(gl::BLEND),
+        Call::BlendEquationSeparate(gl::FUNC_ADD, gl::FUNC_ADD),
+        Call::BlendFuncSeparate(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA, gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA),
+        Call::BlendColor([0.0, 0.0, 0.0, 0.0]),
+        Call::Disable(gl::BLEND),
+    ]);
+}
+
+#[test]
+fn disabling_stencil_restores_write_mask() {
+    setup();
+
+    let state = RenderState::default();
+    state.apply();
+    let calls = mock_gl::calls().len();
+
+    let outline = Stencil {
+        test: Comparison::NotEqual,
+        reference: 1,
+        write_mask: 0,
+        pass: StencilOp::Replace,
+        ..Stencil::default()
+    };
+
+    RenderState { stencil: Some(outline), ..state }.apply();
+    state.apply();
+
+    assert_eq!(&mock_gl::calls()[calls..], &[
+        Call::Enable(gl::STENCIL_TEST),
+        Call::StencilFunc(gl::NOTEQUAL, 1, !0),
+        Call::StencilOp(gl::KEEP, gl::KEEP, gl::REPLACE),
+        Call::StencilMask(0),
+        Call::Disable(gl::STENCIL_TEST),
+        Call::StencilMask(!0),
+    ]);
+}
+
+#[test]
+fn invalidate_applies_everything_again() {
+    setup();
+
+    let state = RenderState::default();
+    state.apply();
+    let calls = mock_gl::calls().len();
+
+    render_state::invalidate();
+    state.apply();
+
+    assert_eq!(mock_gl::calls().len(), 2 * calls);
+}
@@ -0,0 +1,89 @@
+// Tests for the frame timing helpers in `time`. These don't need OpenGL at all.
+extern crate learn_opengl as lgl;
+
+use lgl::time::{Clock, FixedTimestep, TimeSource};
+
+fn assert_close(actual: f32, expected: f32) {
+    assert!((actual - expected).abs() < 1e-5, "expected {}, got {}", expected, actual);
+}
+
+fn count_steps(timestep: &mut FixedTimestep) -> usize {
+    let mut steps = 0;
+
+    while timestep.step() {
+        steps += 1;
+    }
+
+    steps
+}
+
+#[test]
+fn clock_counts_frames() {
+    let mut clock = Clock::new();
+    assert_eq!(clock.frames(), 0);
+    assert_eq!(clock.fps(), 0.0);
+
+    let dt = clock.tick();
+    clock.tick();
+
+    assert_eq!(clock.frames(), 2);
+    assert!(dt >= 0.0 && clock.delta() >= 0.0);
+}
+
+#[test]
+fn fixed_timestep_carries_leftover_time() {
+    let mut timestep = FixedTimestep::new(0.1);
+
+    timestep.accumulate(0.25);
+    assert_eq!(count_steps(&mut timestep), 2);
+    assert_close(timestep.alpha(), 0.5);
+
+    // The leftover 0.05s and the new 0.06s add up to one more step.
+    timestep.accumulate(0.06);
+    assert_eq!(count_steps(&mut timestep), 1);
+    assert_close(timestep.alpha(), 0.1);
+}
+
+#[test]
+fn fixed_timestep_limits_frame_time() {
+    let mut timestep = FixedTimestep::new(0.1).with_max_frame_time(0.35);
+
+    timestep.accumulate(10.0);
+    assert_eq!(count_steps(&mut timestep), 3);
+    assert_close(timestep.alpha(), 0.5);
+}
+
+#[test]
+#[should_panic(expected = "the fixed timestep must be positive")]
+fn fixed_timestep_rejects_zero_step() {
+    FixedTimestep::new(0.0);
+}
+
+#[test]
+fn time_source_scales_time() {
+    let mut time = TimeSource::new();
+    time.set_scale(0.5);
+
+    assert_close(time.advance(0.2), 0.1);
+    assert_close(time.advance(0.2), 0.1);
+    assert_close(time.seconds() as f32, 0.2);
+}
+
+#[test]
+fn time_source_pauses_and_steps() {
+    let mut time = TimeSource::new();
+
+    time.advance(1.0);
+    time.pause();
+    assert_eq!(time.advance(1.0), 0.0);
+
+    // Stepping lets exactly one frame through.
+    time.step_frame();
+    assert_close(time.advance(0.5), 0.5);
+    assert_eq!(time.advance(0.5), 0.0);
+    assert_close(time.seconds() as f32, 1.5);
+
+    time.resume();
+    time.advance(1.0);
+    assert_close(time.seconds() as f32, 2.5);
+}
@@ -0,0 +1,41 @@
+// Tests for the layouts built by `#[derive(Vertex)]`. Building a layout doesn't touch OpenGL, so
+// these don't need a context or the mock.
+extern crate learn_opengl as lgl;
+extern crate learn_opengl_derive;
+
+use std::mem;
+
+use lgl::vertex_array::{AttributeType, Vertex, VertexAttribute};
+
+use learn_opengl_derive::Vertex;
+
+// Mixed field sizes, so that the compiler has to pad before `position` and `weight`.
+#[repr(C)]
+#[derive(Clone, Copy, Vertex)]
+#[allow(dead_code)]
+struct MixedVertex {
+    flags: u8,
+    position: [f32; 3],
+    #[location = 5]
+    weight: f64,
+    #[normalized]
+    color: [u8; 4],
+    #[attribute = "vbo_tex_coord"]
+    tex_coord: [u16; 2],
+}
+
+#[test]
+fn derived_layout_matches_struct() {
+    let layout = MixedVertex::layout();
+
+    assert_eq!(layout.stride(), mem::size_of::<MixedVertex>());
+    assert_eq!(layout.stride(), 32);
+
+    assert_eq!(layout.attributes(), &[
+        (VertexAttribute::new(0, 1, AttributeType::UnsignedByte), 0),
+        (VertexAttribute::float(1, 3), 4),
+        (VertexAttribute::new(5, 1, AttributeType::Double), 16),
+        (VertexAttribute::new(3, 4, AttributeType::UnsignedByte).normalized(), 24),
+        (VertexAttribute::new(4, 2, AttributeType::UnsignedShort).named("vbo_tex_coord"), 28),
+    ][..]);
+}